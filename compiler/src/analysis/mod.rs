@@ -0,0 +1,791 @@
+mod name_resolver;
+
+use crate::item::Item;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::targets::Module;
+use crate::token::{Token, TokenType};
+use crate::transformers::{std_lib_dir, NumberTransformer};
+use name_resolver::NameResolver;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+
+const KEYWORDS: &[&str] = &[
+    "let",
+    "mut",
+    "fn",
+    "cmpnt",
+    "loader",
+    "struct",
+    "import",
+    "if",
+    "else",
+    "true",
+    "false",
+    "print",
+    "assert",
+    "assert_eq",
+    "assert_ne",
+    "panic",
+];
+
+/// The conversions between number types, like `float(count)`.
+const CONVERSIONS: &[&str] = &["int", "i64", "float"];
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Self::Error => write!(f, "error"),
+            Self::Warning => write!(f, "warning"),
+        }
+    }
+}
+
+/// A problem found in a module, at a 1-based line and 0-based column.
+#[derive(Debug, Clone)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    pub line: usize,
+    pub column: usize,
+    pub length: usize,
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SymbolKind {
+    Function,
+    Component,
+    Loader,
+    Struct,
+    Variable,
+    Parameter,
+}
+
+/// A name defined in a module.
+#[derive(Debug, Clone)]
+pub struct Symbol {
+    pub name: String,
+    pub kind: SymbolKind,
+    /// The index of the token that defines the name.
+    pub token: usize,
+    /// How the symbol was declared, e.g. a function's signature.
+    pub detail: String,
+    /// The tokens of the item that defines the symbol, which locals are only visible in.
+    pub scope: Range<usize>,
+}
+
+impl Symbol {
+    pub fn is_item(&self) -> bool {
+        !matches!(self.kind, SymbolKind::Variable | SymbolKind::Parameter)
+    }
+}
+
+/// Where the name at a position is defined.
+pub enum Definition<'a> {
+    Symbol(&'a Symbol),
+    Module(PathBuf),
+    /// An item imported from another module, which has to be analysed to find it.
+    Imported {
+        module: PathBuf,
+        name: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CompletionKind {
+    Symbol(SymbolKind),
+    Module,
+    Keyword,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub label: String,
+    pub kind: CompletionKind,
+    pub detail: Option<String>,
+}
+
+/// An `import` item, with the index of each token in its path.
+#[derive(Debug, Clone)]
+pub struct Import {
+    pub segments: Vec<(String, usize)>,
+}
+
+impl Import {
+    pub fn name(&self) -> &str {
+        &self.segments.last().unwrap().0
+    }
+
+    /// The file of the module the item is imported from.
+    pub fn module_path(&self, dir: &Path) -> PathBuf {
+        let segments = self.segments.iter().map(|(segment, _)| segment.as_str());
+        module_path(
+            dir,
+            &segments
+                .clone()
+                .take(self.segments.len() - 1)
+                .collect::<Vec<_>>(),
+        )
+    }
+}
+
+/// The scanned and, when it is valid, parsed module, along with the names it
+/// defines. Names are collected from the tokens so that they are still known while
+/// the module is being edited and doesn't parse.
+pub struct ModuleAnalysis {
+    pub path: PathBuf,
+    pub source: String,
+    pub tokens: Vec<Token>,
+    pub items: Option<Vec<Item>>,
+    pub symbols: Vec<Symbol>,
+    pub imports: Vec<Import>,
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+impl ModuleAnalysis {
+    pub fn new(path: PathBuf, source: &str) -> Self {
+        let mut scanner = Scanner::new(source.to_string());
+        let tokens = scanner.scan_tokens();
+        let mut diagnostics = scanner
+            .errors()
+            .iter()
+            .map(|error| Diagnostic {
+                severity: Severity::Error,
+                line: error.line,
+                column: error.column,
+                length: 1,
+                message: error.to_string(),
+            })
+            .collect::<Vec<_>>();
+
+        let items = match diagnostics.is_empty() {
+            true => match Parser::new(tokens.clone()).parse() {
+                Ok(items) => Some(items),
+                Err(errors) => {
+                    diagnostics.extend(
+                        errors
+                            .iter()
+                            .map(|error| token_diagnostic(error.token(), error.to_string())),
+                    );
+                    None
+                }
+            },
+            false => None,
+        };
+
+        let mut analysis = Self {
+            path,
+            source: source.to_string(),
+            tokens,
+            items,
+            symbols: Vec::new(),
+            imports: Vec::new(),
+            diagnostics,
+        };
+        analysis.collect_symbols();
+        analysis
+    }
+
+    /// The directory the module's imports are resolved from.
+    pub fn dir(&self) -> &Path {
+        self.path.parent().unwrap_or(Path::new("."))
+    }
+
+    /// The item with the given name.
+    pub fn item(&self, name: &str) -> Option<&Symbol> {
+        self.symbols
+            .iter()
+            .find(|symbol| symbol.is_item() && symbol.name == name)
+    }
+
+    /// The identifier token at a 1-based line and 0-based column.
+    pub fn identifier_at(&self, line: usize, column: usize) -> Option<usize> {
+        self.tokens.iter().position(|token| {
+            token.token_type == TokenType::Identifier
+                && token.line == line
+                && (token.column..=token.column + token.lexeme.len()).contains(&column)
+        })
+    }
+
+    pub fn definition(&self, index: usize) -> Option<Definition<'_>> {
+        let token = &self.tokens[index];
+        if token.token_type != TokenType::Identifier
+            || index > 0 && self.tokens[index - 1].token_type == TokenType::Dot
+        {
+            return None;
+        }
+
+        if let Some(import) = self
+            .imports
+            .iter()
+            .find(|import| import.segments.iter().any(|(_, i)| *i == index))
+        {
+            let position = import.segments.iter().position(|(_, i)| *i == index)?;
+            return match position == import.segments.len() - 1 {
+                true => Some(Definition::Imported {
+                    module: import.module_path(self.dir()),
+                    name: token.lexeme.clone(),
+                }),
+                false => {
+                    let segments = import.segments[..=position]
+                        .iter()
+                        .map(|(segment, _)| segment.as_str())
+                        .collect::<Vec<_>>();
+                    let path = module_path(self.dir(), &segments);
+                    path.is_file().then_some(Definition::Module(path))
+                }
+            };
+        }
+
+        // Locals shadow items, and later declarations shadow earlier ones
+        let local = self.symbols.iter().rev().find(|symbol| {
+            !symbol.is_item()
+                && symbol.name == token.lexeme
+                && symbol.scope.contains(&index)
+                && symbol.token <= index
+        });
+        if let Some(symbol) = local.or(self.item(&token.lexeme)) {
+            return Some(Definition::Symbol(symbol));
+        }
+
+        self.imports
+            .iter()
+            .find(|import| import.name() == token.lexeme)
+            .map(|import| Definition::Imported {
+                module: import.module_path(self.dir()),
+                name: token.lexeme.clone(),
+            })
+    }
+
+    /// Checks the names used by a module, given the modules of its `project`. Only
+    /// imports are checked when the module doesn't parse. Imported modules are analysed with `load`.
+    pub fn check(
+        &self,
+        project: &[ModuleAnalysis],
+        load: impl Fn(&Path) -> Option<ModuleAnalysis>,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = self.import_diagnostics(&load);
+        let Some(items) = &self.items else {
+            return diagnostics;
+        };
+
+        // Components and their loaders share a name
+        let mut defined = HashSet::new();
+        for symbol in self.symbols.iter().filter(|symbol| symbol.is_item()) {
+            if !defined.insert((&symbol.name, symbol.kind == SymbolKind::Loader)) {
+                diagnostics.push(token_diagnostic(
+                    &self.tokens[symbol.token],
+                    format!("`{}` is defined multiple times", symbol.name),
+                ));
+            }
+        }
+
+        // Every module of a project compiles into the same Go package, so items
+        // are visible across modules
+        let imported = self
+            .imports
+            .iter()
+            .filter_map(|import| load(&import.module_path(self.dir())))
+            .collect::<Vec<_>>();
+        let mut globals = project
+            .iter()
+            .chain(std::iter::once(self))
+            .flat_map(|module| module.symbols.iter().filter(|symbol| symbol.is_item()))
+            .map(|symbol| symbol.name.clone())
+            .collect::<HashSet<_>>();
+        globals.extend(self.imports.iter().map(|import| import.name().to_string()));
+        globals.extend(CONVERSIONS.iter().map(|name| name.to_string()));
+        let structs = project
+            .iter()
+            .chain(&imported)
+            .chain(std::iter::once(self))
+            .flat_map(|module| module.items.iter().flatten())
+            .filter_map(|item| match item {
+                Item::Struct { name, fields, .. } => Some((
+                    name.clone(),
+                    fields.iter().map(|field| field.name.clone()).collect(),
+                )),
+                _ => None,
+            })
+            .collect::<HashMap<_, _>>();
+
+        diagnostics.extend(NameResolver::new(&globals, &structs).resolve(items));
+        diagnostics.extend(self.number_diagnostics(project, items));
+        diagnostics
+    }
+
+    /// Checks the types of the numbers in a module, with the functions of the other
+    /// modules of its project.
+    fn number_diagnostics(&self, project: &[ModuleAnalysis], items: &[Item]) -> Vec<Diagnostic> {
+        let mut program = project
+            .iter()
+            .filter(|module| module.path != self.path)
+            .filter_map(|module| {
+                Some(Module {
+                    path: module.path.clone(),
+                    items: module.items.clone()?,
+                    source: None,
+                })
+            })
+            .chain(std::iter::once(Module {
+                path: self.path.clone(),
+                items: items.to_vec(),
+                source: None,
+            }))
+            .collect::<Vec<_>>();
+        NumberTransformer::new()
+            .check(&mut program)
+            .into_iter()
+            .filter(|(module, ..)| *module == self.path)
+            .map(|(_, line, message)| self.line_diagnostic(line, message))
+            .collect()
+    }
+
+    /// An error that spans the tokens of a 1-based line.
+    fn line_diagnostic(&self, line: usize, message: String) -> Diagnostic {
+        let mut tokens = self.tokens.iter().filter(|token| token.line == line);
+        let (column, end) = match (tokens.next(), tokens.next_back()) {
+            (Some(first), last) => {
+                let last = last.unwrap_or(first);
+                (first.column, last.column + last.lexeme.len())
+            }
+            (None, _) => (0, 1),
+        };
+        Diagnostic {
+            severity: Severity::Error,
+            line,
+            column,
+            length: (end - column).max(1),
+            message,
+        }
+    }
+
+    /// Checks that every import names an item of an existing module.
+    fn import_diagnostics(
+        &self,
+        load: impl Fn(&Path) -> Option<ModuleAnalysis>,
+    ) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        for import in self
+            .imports
+            .iter()
+            .filter(|import| import.segments.len() > 1)
+        {
+            let (name, index) = import.segments.last().unwrap();
+            let module_name = import.segments[..import.segments.len() - 1]
+                .iter()
+                .map(|(segment, _)| segment.as_str())
+                .collect::<Vec<_>>()
+                .join("::");
+            match load(&import.module_path(self.dir())) {
+                None => diagnostics.push(token_diagnostic(
+                    &self.tokens[import.segments[0].1],
+                    format!("Unresolved module `{}`", module_name),
+                )),
+                Some(module) if module.item(name).is_none() => diagnostics.push(token_diagnostic(
+                    &self.tokens[*index],
+                    format!("`{}` is not defined in `{}`", name, module_name),
+                )),
+                Some(_) => {}
+            }
+        }
+        diagnostics
+    }
+
+    /// The completions for the identifier being typed at a 1-based line and
+    /// 0-based column. Modules from `load` are used to complete imported items.
+    pub fn completions(
+        &self,
+        line: usize,
+        column: usize,
+        load: impl Fn(&Path) -> Option<ModuleAnalysis>,
+    ) -> Vec<Completion> {
+        // Tokens that end before the cursor, leaving out the identifier being typed
+        let mut cursor = self
+            .tokens
+            .iter()
+            .take_while(|token| (token.line, token.column) < (line, column))
+            .count();
+        if cursor > 0 && self.tokens[cursor - 1].token_type == TokenType::Identifier {
+            let token = &self.tokens[cursor - 1];
+            if token.line == line && token.column + token.lexeme.len() >= column {
+                cursor -= 1;
+            }
+        }
+
+        if let Some(segments) = self.import_prefix(cursor) {
+            return self.import_completions(&segments, load);
+        }
+
+        let mut completions = KEYWORDS
+            .iter()
+            .map(|keyword| Completion {
+                label: keyword.to_string(),
+                kind: CompletionKind::Keyword,
+                detail: None,
+            })
+            .collect::<Vec<_>>();
+        let visible = self.symbols.iter().filter(|symbol| {
+            symbol.is_item() || symbol.scope.contains(&cursor) && symbol.token < cursor
+        });
+        for symbol in visible {
+            completions.push(Completion {
+                label: symbol.name.clone(),
+                kind: CompletionKind::Symbol(symbol.kind),
+                detail: Some(symbol.detail.clone()),
+            });
+        }
+        for import in &self.imports {
+            let symbol = load(&import.module_path(self.dir()))
+                .and_then(|module| module.item(import.name()).cloned());
+            completions.push(Completion {
+                label: import.name().to_string(),
+                kind: symbol.as_ref().map_or(CompletionKind::Module, |symbol| {
+                    CompletionKind::Symbol(symbol.kind)
+                }),
+                detail: symbol.map(|symbol| symbol.detail),
+            });
+        }
+
+        // Later declarations shadow earlier ones
+        let mut seen = std::collections::HashSet::new();
+        completions.reverse();
+        completions.retain(|completion| seen.insert(completion.label.clone()));
+        completions.reverse();
+        completions
+    }
+
+    /// The path segments already typed when the cursor is inside an import.
+    fn import_prefix(&self, cursor: usize) -> Option<Vec<String>> {
+        let start = self.tokens[..cursor].iter().rposition(|token| {
+            token.token_type != TokenType::Identifier && token.token_type != TokenType::ColonColon
+        })?;
+        if self.tokens[start].token_type != TokenType::Import {
+            return None;
+        }
+        Some(
+            self.tokens[start + 1..cursor]
+                .iter()
+                .filter(|token| token.token_type == TokenType::Identifier)
+                .map(|token| token.lexeme.clone())
+                .collect(),
+        )
+    }
+
+    fn import_completions(
+        &self,
+        segments: &[String],
+        load: impl Fn(&Path) -> Option<ModuleAnalysis>,
+    ) -> Vec<Completion> {
+        let segments = segments.iter().map(String::as_str).collect::<Vec<_>>();
+        let module = module_path(self.dir(), &segments);
+        if module.is_file() {
+            return load(&module)
+                .map(|module| {
+                    module
+                        .symbols
+                        .into_iter()
+                        .filter(Symbol::is_item)
+                        .map(|symbol| Completion {
+                            label: symbol.name,
+                            kind: CompletionKind::Symbol(symbol.kind),
+                            detail: Some(symbol.detail),
+                        })
+                        .collect()
+                })
+                .unwrap_or_default();
+        }
+
+        let mut completions = Vec::new();
+        if segments.is_empty() {
+            completions.push(Completion {
+                label: "std".to_string(),
+                kind: CompletionKind::Module,
+                detail: None,
+            });
+        }
+        for entry in std::fs::read_dir(module.with_extension(""))
+            .into_iter()
+            .flatten()
+        {
+            let path = entry.map(|entry| entry.path()).unwrap_or_default();
+            let is_hidden = path
+                .file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+            let is_module = path.is_dir() && !is_hidden
+                || path.extension().is_some_and(|ext| ext == "fsf") && path != self.path;
+            if let (true, Some(name)) = (is_module, path.file_stem()) {
+                completions.push(Completion {
+                    label: name.to_string_lossy().to_string(),
+                    kind: CompletionKind::Module,
+                    detail: None,
+                });
+            }
+        }
+        completions
+    }
+
+    fn collect_symbols(&mut self) {
+        let item_starts = self.item_starts();
+        let tokens = &self.tokens;
+        let scope_of = |index: usize| {
+            let start = item_starts.iter().rev().find(|start| **start <= index);
+            let end = item_starts.iter().find(|start| **start > index);
+            start.copied().unwrap_or(0)..end.copied().unwrap_or(tokens.len())
+        };
+
+        let mut symbols = Vec::new();
+        let mut imports = Vec::new();
+        let is_identifier = |i: usize| {
+            tokens
+                .get(i)
+                .is_some_and(|token| token.token_type == TokenType::Identifier)
+        };
+        for (i, token) in tokens.iter().enumerate() {
+            match token.token_type {
+                TokenType::Fn | TokenType::Cmpnt | TokenType::Loader | TokenType::Struct
+                    if is_identifier(i + 1) =>
+                {
+                    let (kind, keyword) = match token.token_type {
+                        TokenType::Fn => (SymbolKind::Function, "fn"),
+                        TokenType::Cmpnt => (SymbolKind::Component, "cmpnt"),
+                        TokenType::Loader => (SymbolKind::Loader, "loader"),
+                        _ => (SymbolKind::Struct, "struct"),
+                    };
+                    let name = tokens[i + 1].lexeme.clone();
+                    symbols.push(Symbol {
+                        detail: self
+                            .items
+                            .iter()
+                            .flatten()
+                            .find_map(|item| signature(item, &name))
+                            .unwrap_or(format!("{} {}", keyword, name)),
+                        name,
+                        kind,
+                        token: i + 1,
+                        scope: scope_of(i),
+                    });
+
+                    if kind != SymbolKind::Struct {
+                        symbols.extend(parameters(tokens, i + 2, scope_of(i)));
+                    }
+                }
+                TokenType::Let => {
+                    let mutable = tokens.get(i + 1).map(|t| &t.token_type) == Some(&TokenType::Mut);
+                    let start = if mutable { i + 2 } else { i + 1 };
+                    // Tuple and array declarations bind every name inside the brackets
+                    let names = match tokens.get(start).map(|t| &t.token_type) {
+                        Some(TokenType::LeftParen | TokenType::LeftSquareBracket) => tokens
+                            [start + 1..]
+                            .iter()
+                            .enumerate()
+                            .take_while(|(_, t)| {
+                                !matches!(
+                                    t.token_type,
+                                    TokenType::RightParen | TokenType::RightSquareBracket
+                                )
+                            })
+                            .filter(|(_, t)| t.token_type == TokenType::Identifier)
+                            .map(|(offset, _)| start + 1 + offset)
+                            .collect(),
+                        Some(TokenType::Identifier) => vec![start],
+                        _ => vec![],
+                    };
+                    for index in names {
+                        let name = tokens[index].lexeme.clone();
+                        symbols.push(Symbol {
+                            detail: match mutable {
+                                true => format!("let mut {}", name),
+                                false => format!("let {}", name),
+                            },
+                            name,
+                            kind: SymbolKind::Variable,
+                            token: index,
+                            scope: scope_of(index),
+                        });
+                    }
+                }
+                TokenType::Import => {
+                    let segments = tokens[i + 1..]
+                        .iter()
+                        .enumerate()
+                        .take_while(|(_, t)| {
+                            matches!(t.token_type, TokenType::Identifier | TokenType::ColonColon)
+                        })
+                        .filter(|(_, t)| t.token_type == TokenType::Identifier)
+                        .map(|(offset, t)| (t.lexeme.clone(), i + 1 + offset))
+                        .collect::<Vec<_>>();
+                    if !segments.is_empty() {
+                        imports.push(Import { segments });
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        self.symbols = symbols;
+        self.imports = imports;
+    }
+
+    /// The indices of the tokens that start top level items.
+    fn item_starts(&self) -> Vec<usize> {
+        let mut depth = 0;
+        let mut starts = Vec::new();
+        for (i, token) in self.tokens.iter().enumerate() {
+            match token.token_type {
+                TokenType::LeftBrace | TokenType::LeftParen | TokenType::LeftSquareBracket => {
+                    depth += 1
+                }
+                TokenType::RightBrace | TokenType::RightParen | TokenType::RightSquareBracket => {
+                    depth -= 1
+                }
+                // Attributes start the item they are attached to
+                TokenType::Fn
+                    if i > 0 && self.tokens[i - 1].token_type == TokenType::RightSquareBracket => {}
+                TokenType::Fn
+                | TokenType::Cmpnt
+                | TokenType::Loader
+                | TokenType::Struct
+                | TokenType::Import
+                | TokenType::TestRunner
+                | TokenType::Hash
+                    if depth == 0 =>
+                {
+                    starts.push(i)
+                }
+                _ => {}
+            }
+        }
+        starts
+    }
+}
+
+/// The file of the module at an import path, relative to the importing module's
+/// directory unless it is part of the standard library.
+pub fn module_path(dir: &Path, segments: &[&str]) -> PathBuf {
+    let (root, segments) = match segments.split_first() {
+        Some((&"std", rest)) => (std_lib_dir(), rest),
+        _ => (dir.to_path_buf(), segments),
+    };
+    let path = segments
+        .iter()
+        .fold(root, |path, segment| path.join(segment));
+    match segments.is_empty() {
+        true => path,
+        false => path.with_extension("fsf"),
+    }
+}
+
+/// How an item is declared, without its body.
+pub fn signature(item: &Item, name: &str) -> Option<String> {
+    let parameters = |parameters: &[crate::item::Parameter]| {
+        parameters
+            .iter()
+            .map(|p| format!("{}: {}", p.name, p.type_annotation))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let return_type = |return_type: &Option<String>| {
+        return_type
+            .as_ref()
+            .map(|t| format!(" -> {}", t))
+            .unwrap_or_default()
+    };
+
+    match item {
+        Item::Function {
+            name: n,
+            type_parameters,
+            parameters: p,
+            return_type: r,
+            ..
+        } if n == name => Some(format!(
+            "fn {}{}({}){}",
+            n,
+            match type_parameters.is_empty() {
+                true => String::new(),
+                false => format!("<{}>", type_parameters.join(", ")),
+            },
+            parameters(p),
+            return_type(r)
+        )),
+        Item::Action {
+            name: n,
+            parameters: p,
+            return_type: r,
+            ..
+        } if n == name => Some(format!(
+            "#[server]\nfn {}({}){}",
+            n,
+            parameters(p),
+            return_type(r)
+        )),
+        Item::Component {
+            name: n,
+            parameters: p,
+            ..
+        } if n == name => Some(format!("cmpnt {}({})", n, parameters(p))),
+        Item::Loader {
+            name: n,
+            return_type,
+            ..
+        } if n == name => Some(format!("loader {}() -> {}", n, return_type)),
+        Item::Struct {
+            name: n, fields, ..
+        } if n == name => Some(format!(
+            "struct {} {{\n{}}}",
+            n,
+            fields
+                .iter()
+                .map(|field| format!("    {}: {},\n", field.name, field.type_annotation))
+                .collect::<String>()
+        )),
+        _ => None,
+    }
+}
+
+/// The parameters of the function whose name is followed by the token at `start`,
+/// skipping over any type parameters.
+fn parameters(tokens: &[Token], start: usize, scope: Range<usize>) -> Vec<Symbol> {
+    let mut parameters = Vec::new();
+    let Some(open) = tokens[start..]
+        .iter()
+        .position(|token| token.token_type == TokenType::LeftParen)
+    else {
+        return parameters;
+    };
+    let mut i = start + open + 1;
+    while i + 2 < tokens.len() && tokens[i].token_type != TokenType::RightParen {
+        if tokens[i].token_type == TokenType::Identifier
+            && tokens[i + 1].token_type == TokenType::Colon
+        {
+            let name = tokens[i].lexeme.clone();
+            parameters.push(Symbol {
+                detail: format!("{}: {}", name, tokens[i + 2].lexeme),
+                name,
+                kind: SymbolKind::Parameter,
+                token: i,
+                scope: scope.clone(),
+            });
+        }
+        i += 1;
+    }
+    parameters
+}
+
+fn token_diagnostic(token: &Token, message: String) -> Diagnostic {
+    Diagnostic {
+        severity: Severity::Error,
+        line: token.line,
+        column: token.column,
+        length: token.lexeme.len().max(1),
+        message,
+    }
+}
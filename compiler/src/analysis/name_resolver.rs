@@ -0,0 +1,322 @@
+use super::{token_diagnostic, Diagnostic, Severity};
+use crate::expression::{
+    BlockExpression, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk,
+};
+use crate::item::Item;
+use crate::statement::{Declaration, Statement};
+use crate::token::Token;
+use std::collections::{HashMap, HashSet};
+
+/// A local variable or parameter.
+struct Binding {
+    name: String,
+    /// Where a variable was declared. Parameters have no token and are never
+    /// reported as unused.
+    token: Option<Token>,
+    used: bool,
+}
+
+/// Checks that every name used in a module's items is defined where it is used, that
+/// struct literals match their struct and that every local variable is used, which Go
+/// requires.
+pub struct NameResolver<'a> {
+    /// The names visible everywhere in the module: the items of the project and the
+    /// items the module imports.
+    globals: &'a HashSet<String>,
+    /// The fields of every struct the module can use, by the struct's name.
+    structs: &'a HashMap<String, Vec<String>>,
+    scopes: Vec<Vec<Binding>>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> NameResolver<'a> {
+    pub fn new(globals: &'a HashSet<String>, structs: &'a HashMap<String, Vec<String>>) -> Self {
+        Self {
+            globals,
+            structs,
+            scopes: Vec::new(),
+            diagnostics: Vec::new(),
+        }
+    }
+
+    pub fn resolve(mut self, items: &[Item]) -> Vec<Diagnostic> {
+        for item in items {
+            self.item(item);
+        }
+        self.diagnostics
+    }
+
+    fn item(&mut self, item: &Item) {
+        let (parameters, body) = match item {
+            Item::Function {
+                parameters, body, ..
+            }
+            | Item::Action {
+                parameters, body, ..
+            }
+            | Item::Component {
+                parameters, body, ..
+            } => (parameters.as_slice(), body),
+            Item::Loader { body, .. } => (&[][..], body),
+            Item::Import { .. } | Item::TestRunner | Item::Router { .. } | Item::Struct { .. } => {
+                return
+            }
+        };
+
+        self.scopes.push(Vec::new());
+        for parameter in parameters {
+            self.declare(&parameter.name, None);
+        }
+        self.block(body);
+        self.pop_scope();
+    }
+
+    fn block(&mut self, block: &BlockExpression) {
+        self.scopes.push(Vec::new());
+        for (_, statement) in &block.statements {
+            self.statement(statement);
+        }
+        if let Some(expr) = &block.expr {
+            self.expression_without_block(expr);
+        }
+        self.pop_scope();
+    }
+
+    fn statement(&mut self, statement: &Statement) {
+        match statement {
+            Statement::Print(expression) | Statement::Expression(expression) => {
+                self.expression(expression)
+            }
+            Statement::Panic(message) => self.expression(message),
+            Statement::Assert { condition, message } => {
+                self.expression(&condition.expression);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::AssertEq {
+                left,
+                right,
+                message,
+            }
+            | Statement::AssertNe {
+                left,
+                right,
+                message,
+            } => {
+                self.expression(&left.expression);
+                self.expression(&right.expression);
+                if let Some(message) = message {
+                    self.expression(message);
+                }
+            }
+            Statement::RunTest {
+                function_name,
+                options,
+                ..
+            } => {
+                self.expression(function_name);
+                for hook in options.setup.iter().chain(&options.teardown) {
+                    self.expression(hook);
+                }
+            }
+            Statement::Let {
+                declaration,
+                expression,
+                ..
+            } => {
+                // The declared names aren't visible in their own initializer
+                self.expression(expression);
+                let names = match declaration {
+                    Declaration::Name(name) => vec![name],
+                    Declaration::Array(names) | Declaration::Tuple(names) => names.iter().collect(),
+                };
+                for name in names {
+                    self.declare(&name.lexeme, Some(name.clone()));
+                }
+            }
+        }
+    }
+
+    fn expression(&mut self, expression: &Expression) {
+        match expression {
+            Expression::WithBlock(expr) => self.expression_with_block(expr),
+            Expression::WithoutBlock(expr) => self.expression_without_block(expr),
+        }
+    }
+
+    fn expression_with_block(&mut self, expr: &ExpressionWithBlock) {
+        match expr {
+            ExpressionWithBlock::Block(block) => self.block(block),
+            ExpressionWithBlock::If { expr, then, r#else } => {
+                self.expression(expr);
+                self.block(then);
+                if let Some(r#else) = r#else {
+                    self.expression_with_block(r#else);
+                }
+            }
+        }
+    }
+
+    fn expression_without_block(&mut self, expr: &ExpressionWithoutBlock) {
+        match expr {
+            ExpressionWithoutBlock::Variable(name) => self.lookup(name),
+            ExpressionWithoutBlock::Assignment { name, value, .. } => {
+                self.expression_without_block(value);
+                // Assigning to a variable doesn't count as using it
+                if self.find(&name.lexeme).is_none() && !self.globals.contains(&name.lexeme) {
+                    self.undefined(name);
+                }
+            }
+            ExpressionWithoutBlock::Binary { left, right, .. } => {
+                self.expression_without_block(left);
+                self.expression_without_block(right);
+            }
+            ExpressionWithoutBlock::Call { callee, arguments } => {
+                self.expression_without_block(callee);
+                for argument in arguments {
+                    self.expression(argument);
+                }
+            }
+            ExpressionWithoutBlock::Index { callee, index } => {
+                self.expression_without_block(callee);
+                self.expression(index);
+            }
+            ExpressionWithoutBlock::Field { callee, .. } => self.expression_without_block(callee),
+            ExpressionWithoutBlock::Convert { expression, .. } => self.expression(expression),
+            ExpressionWithoutBlock::Lambda { parameters, body } => {
+                self.scopes.push(Vec::new());
+                for parameter in parameters {
+                    self.declare(&parameter.name, None);
+                }
+                self.expression(body);
+                self.pop_scope();
+            }
+            ExpressionWithoutBlock::Grouping(expr)
+            | ExpressionWithoutBlock::Unary { right: expr, .. } => {
+                self.expression_without_block(expr)
+            }
+            ExpressionWithoutBlock::Html {
+                name,
+                inner,
+                attributes,
+            } => {
+                // Capitalized elements are components, the rest are HTML elements
+                if name.lexeme.starts_with(char::is_uppercase) {
+                    self.lookup(name);
+                }
+                for (_, value) in attributes {
+                    self.expression(value);
+                }
+                for expression in inner {
+                    self.expression(expression);
+                }
+            }
+            ExpressionWithoutBlock::FString { chunks } => {
+                for chunk in chunks {
+                    if let FStringChunk::Expression { expression, .. } = chunk {
+                        self.expression(expression);
+                    }
+                }
+            }
+            ExpressionWithoutBlock::Array { elements, .. }
+            | ExpressionWithoutBlock::Tuple { elements } => {
+                for element in elements {
+                    self.expression(element);
+                }
+            }
+            ExpressionWithoutBlock::Struct { name, fields } => {
+                for (_, value) in fields {
+                    self.expression(value);
+                }
+                self.struct_literal(name, fields);
+            }
+            ExpressionWithoutBlock::Literal(_)
+            | ExpressionWithoutBlock::RawJs(_)
+            | ExpressionWithoutBlock::RawGo(_) => {}
+        }
+    }
+
+    fn struct_literal(&mut self, name: &Token, fields: &[(Token, Expression)]) {
+        let Some(definition) = self.structs.get(&name.lexeme) else {
+            self.diagnostics.push(token_diagnostic(
+                name,
+                format!("cannot find struct `{}`", name.lexeme),
+            ));
+            return;
+        };
+
+        for (field, _) in fields {
+            if !definition.contains(&field.lexeme) {
+                self.diagnostics.push(token_diagnostic(
+                    field,
+                    format!(
+                        "struct `{}` has no field named `{}`",
+                        name.lexeme, field.lexeme
+                    ),
+                ));
+            }
+        }
+        let missing = definition
+            .iter()
+            .filter(|field| !fields.iter().any(|(f, _)| &f.lexeme == *field))
+            .map(|field| format!("`{}`", field))
+            .collect::<Vec<_>>();
+        if !missing.is_empty() {
+            self.diagnostics.push(token_diagnostic(
+                name,
+                format!(
+                    "missing fields {} in initializer of `{}`",
+                    missing.join(", "),
+                    name.lexeme
+                ),
+            ));
+        }
+    }
+
+    fn declare(&mut self, name: &str, token: Option<Token>) {
+        self.scopes.last_mut().unwrap().push(Binding {
+            name: name.to_string(),
+            token,
+            used: false,
+        });
+    }
+
+    fn find(&mut self, name: &str) -> Option<&mut Binding> {
+        self.scopes
+            .iter_mut()
+            .rev()
+            .flat_map(|scope| scope.iter_mut().rev())
+            .find(|binding| binding.name == name)
+    }
+
+    fn lookup(&mut self, name: &Token) {
+        let is_global = self.globals.contains(&name.lexeme);
+        match self.find(&name.lexeme) {
+            Some(binding) => binding.used = true,
+            None if is_global => {}
+            None => self.undefined(name),
+        }
+    }
+
+    fn undefined(&mut self, name: &Token) {
+        self.diagnostics.push(token_diagnostic(
+            name,
+            format!("cannot find `{}` in this scope", name.lexeme),
+        ));
+    }
+
+    fn pop_scope(&mut self) {
+        for binding in self.scopes.pop().unwrap() {
+            match binding.token {
+                Some(token) if !binding.used && !binding.name.starts_with('_') => {
+                    self.diagnostics.push(Diagnostic {
+                        severity: Severity::Warning,
+                        ..token_diagnostic(&token, format!("unused variable `{}`", binding.name))
+                    })
+                }
+                _ => {}
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+package main
+
+import (
+	"fmt"
+	"html"
+	"strings"
+)
+
+// HTML is markup that has already been rendered, like the children of a component,
+// and is added to a builder without being escaped
+type HTML string
+
+// toHTML escapes a value as text unless it is already HTML
+func toHTML(value any) HTML {
+	if markup, ok := value.(HTML); ok {
+		return markup
+	}
+	return HTML(html.EscapeString(fmt.Sprint(value)))
+}
+
+// HTMLBuilder provides a simple API for building HTML strings
+type HTMLBuilder struct {
+	elements     []string // Stack to track open elements
+	buffer       strings.Builder
+	startTagOpen bool // Whether attributes can still be added to the last element
+}
+
+// NewHTMLBuilder creates a new HTMLBuilder instance
+func NewHTMLBuilder() *HTMLBuilder {
+	return &HTMLBuilder{
+		elements: make([]string, 0),
+	}
+}
+
+// beginElement starts a new HTML element
+func (h *HTMLBuilder) beginElement(name string) {
+	h.closeStartTag()
+	h.buffer.WriteString("<")
+	h.buffer.WriteString(name)
+	h.elements = append(h.elements, name)
+	h.startTagOpen = true
+}
+
+// endElement closes the most recently opened element
+func (h *HTMLBuilder) endElement() {
+	if len(h.elements) == 0 {
+		return // No elements to close
+	}
+	h.closeStartTag()
+
+	// Pop the last element from the stack
+	lastIndex := len(h.elements) - 1
+	elementName := h.elements[lastIndex]
+	h.elements = h.elements[:lastIndex]
+
+	// Write the closing tag
+	h.buffer.WriteString("</")
+	h.buffer.WriteString(elementName)
+	h.buffer.WriteString(">")
+}
+
+// addString adds text content to the current element
+func (h *HTMLBuilder) addString(text string) {
+	h.closeStartTag()
+	h.buffer.WriteString(text)
+}
+
+// addChild adds an interpolated value to the current element, escaping it as text
+func (h *HTMLBuilder) addChild(value any) {
+	h.closeStartTag()
+	h.buffer.WriteString(string(toHTML(value)))
+}
+
+// addAttribute adds an attribute to the element that was just begun. Values that
+// only make sense on the client, such as event handlers, are not rendered.
+func (h *HTMLBuilder) addAttribute(name string, value interface{}) {
+	if !h.startTagOpen {
+		return
+	}
+
+	switch value.(type) {
+	case string, int, int32, int64, float64, bool:
+		h.buffer.WriteString(fmt.Sprintf(` %s="%s"`, name, html.EscapeString(fmt.Sprint(value))))
+	}
+}
+
+// build returns the final HTML string
+func (h *HTMLBuilder) build() string {
+	h.closeStartTag()
+	return h.buffer.String()
+}
+
+// Reset clears the builder for reuse
+func (h *HTMLBuilder) Reset() {
+	h.elements = h.elements[:0]
+	h.buffer.Reset()
+	h.startTagOpen = false
+}
+
+func (h *HTMLBuilder) closeStartTag() {
+	if h.startTagOpen {
+		h.buffer.WriteString(">")
+		h.startTagOpen = false
+	}
+}
@@ -0,0 +1,89 @@
+package main
+
+import (
+	"sort"
+	"strings"
+)
+
+type fsfRoute struct {
+	component string
+	entry     string // The route's JavaScript entry point
+	load      func(params map[string]string) any
+	render    func(params map[string]string, data any) string
+}
+
+// fsfParams is what use_params returns outside of a route. Components, loaders and
+// hooks are passed the params of the request they render instead.
+var fsfParams = map[string]string{}
+
+// matchRoute finds the route serving a path. Static routes take precedence over
+// routes with dynamic `[name]` segments, whose values are returned as params.
+func matchRoute(routes map[string]fsfRoute, path string) (fsfRoute, map[string]string, bool) {
+	if route, ok := routes[path]; ok {
+		return route, map[string]string{}, true
+	}
+
+	segments := strings.Split(strings.Trim(path, "/"), "/")
+	for _, pattern := range sortedPatterns(routes) {
+		patternSegments := strings.Split(strings.Trim(pattern, "/"), "/")
+		if len(patternSegments) != len(segments) {
+			continue
+		}
+
+		params := map[string]string{}
+		matched := true
+		for i, segment := range patternSegments {
+			if isDynamicSegment(segment) {
+				params[segment[1:len(segment)-1]] = segments[i]
+			} else if segment != segments[i] {
+				matched = false
+				break
+			}
+		}
+		if matched {
+			return routes[pattern], params, true
+		}
+	}
+
+	return fsfRoute{}, nil, false
+}
+
+// sortedPatterns orders the patterns of routes so that matching doesn't depend on
+// the order of the map. At the first segment where two patterns differ, a static
+// segment comes before a dynamic one, so `/chirps/new` is matched before `/chirps/[id]`.
+func sortedPatterns(routes map[string]fsfRoute) []string {
+	patterns := make([]string, 0, len(routes))
+	for pattern := range routes {
+		patterns = append(patterns, pattern)
+	}
+	sort.Slice(patterns, func(i, j int) bool {
+		a := strings.Split(strings.Trim(patterns[i], "/"), "/")
+		b := strings.Split(strings.Trim(patterns[j], "/"), "/")
+		for k := 0; k < len(a) && k < len(b); k++ {
+			if a[k] == b[k] {
+				continue
+			}
+			if isDynamicSegment(a[k]) != isDynamicSegment(b[k]) {
+				return !isDynamicSegment(a[k])
+			}
+			return a[k] < b[k]
+		}
+		return len(a) < len(b)
+	})
+	return patterns
+}
+
+func isDynamicSegment(segment string) bool {
+	return strings.HasPrefix(segment, "[") && strings.HasSuffix(segment, "]")
+}
+
+// renderRoute loads and renders a route with its params available to use_params.
+func renderRoute(route fsfRoute, params map[string]string) (string, any) {
+	data := route.load(params)
+	return route.render(params, data), data
+}
+
+// loadRoute runs the loader of a route with its params available to use_params.
+func loadRoute(route fsfRoute, params map[string]string) any {
+	return route.load(params)
+}
@@ -0,0 +1,120 @@
+package main
+
+import (
+	"encoding/json"
+	"mime"
+	"net/http"
+	"net/url"
+	"strings"
+	"sync"
+)
+
+// serverAction decodes the JSON encoded arguments sent by the client, calls the
+// underlying #[server] function and returns its result.
+type serverAction func(arguments []json.RawMessage) (any, error)
+
+var serverActions = map[string]serverAction{}
+
+func registerServerAction(name string, action serverAction) {
+	serverActions[name] = action
+}
+
+// decodeArgument decodes an argument of a server action. i64 values are sent as strings
+// since JSON numbers can't hold them in JavaScript, so numbers are also read from strings.
+func decodeArgument(argument json.RawMessage, value any) error {
+	err := json.Unmarshal(argument, value)
+	if _, ok := err.(*json.UnmarshalTypeError); ok && strings.HasPrefix(string(argument), `"`) {
+		var text string
+		if json.Unmarshal(argument, &text) == nil && json.Unmarshal([]byte(text), value) == nil {
+			return nil
+		}
+	}
+	return err
+}
+
+// isSameOrigin reports whether a request was made by a page served from this server.
+// Browsers send the Origin of cross-site requests, while other clients may leave it out.
+func isSameOrigin(r *http.Request) bool {
+	origin := r.Header.Get("Origin")
+	if origin == "" {
+		return true
+	}
+	parsed, err := url.Parse(origin)
+	return err == nil && parsed.Host == r.Host
+}
+
+// handleServerAction serves POST /__actions/{name} requests made by the
+// generated client stubs. Only JSON requests from the same origin are accepted, which
+// forms on other sites can't send.
+func handleServerAction(w http.ResponseWriter, r *http.Request) {
+	if r.Method != http.MethodPost {
+		http.Error(w, "server actions must be called with POST", http.StatusMethodNotAllowed)
+		return
+	}
+
+	mediaType, _, err := mime.ParseMediaType(r.Header.Get("Content-Type"))
+	if err != nil || mediaType != "application/json" {
+		http.Error(w, "server actions must be called with JSON", http.StatusUnsupportedMediaType)
+		return
+	}
+	if !isSameOrigin(r) {
+		http.Error(w, "server actions can't be called from other origins", http.StatusForbidden)
+		return
+	}
+
+	action, ok := serverActions[strings.TrimPrefix(r.URL.Path, "/__actions/")]
+	if !ok {
+		http.NotFound(w, r)
+		return
+	}
+
+	var arguments []json.RawMessage
+	if err := json.NewDecoder(r.Body).Decode(&arguments); err != nil {
+		http.Error(w, err.Error(), http.StatusBadRequest)
+		return
+	}
+
+	result, err := action(arguments)
+	if err != nil {
+		http.Error(w, err.Error(), http.StatusBadRequest)
+		return
+	}
+
+	w.Header().Set("Content-Type", "application/json")
+	json.NewEncoder(w).Encode(result)
+}
+
+// fsfServerList is a list of `std::server`, which requests may use at the same time.
+type fsfServerList[T any] struct {
+	mutex sync.Mutex
+	items []T
+}
+
+// fsfServerLists are the lists of `std::server`, by name.
+var fsfServerLists = map[string]any{}
+var fsfServerListsMutex sync.Mutex
+
+// fsfServerListNamed returns the list named `name`, which starts out with `first`.
+func fsfServerListNamed[T any](name string, first T) *fsfServerList[T] {
+	fsfServerListsMutex.Lock()
+	defer fsfServerListsMutex.Unlock()
+	if list, ok := fsfServerLists[name]; ok {
+		return list.(*fsfServerList[T])
+	}
+	list := &fsfServerList[T]{items: []T{first}}
+	fsfServerLists[name] = list
+	return list
+}
+
+func (list *fsfServerList[T]) push(value T) {
+	list.mutex.Lock()
+	defer list.mutex.Unlock()
+	list.items = append(list.items, value)
+}
+
+// values copies the items, so that they can be read while others are pushed.
+func (list *fsfServerList[T]) values() []T {
+	list.mutex.Lock()
+	defer list.mutex.Unlock()
+	return append([]T(nil), list.items...)
+}
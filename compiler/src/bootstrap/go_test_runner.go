@@ -0,0 +1,178 @@
+package main
+
+import (
+	"encoding/json"
+	"fmt"
+	"os"
+	"os/exec"
+	"runtime"
+	"strings"
+	"time"
+)
+
+var testsPassed, testsFailed, testsIgnored int
+
+// fsf sets FSF_TEST_FORMAT=json to read each result as a line of JSON
+var testsAsJson = os.Getenv("FSF_TEST_FORMAT") == "json"
+var testsIncludeIgnored = os.Getenv("FSF_TEST_INCLUDE_IGNORED") != ""
+
+// With FSF_TEST_PARALLEL set every test runs in a process of its own, which
+// only runs the test named by FSF_TEST_ONLY, so that their output doesn't
+// interleave. The results are reported in the order the tests are declared.
+var testsInParallel = os.Getenv("FSF_TEST_PARALLEL") != ""
+var onlyTest = os.Getenv("FSF_TEST_ONLY")
+var parallelTests []func()
+var parallelSlots = make(chan struct{}, runtime.NumCPU())
+
+type testOptions struct {
+	setup, teardown func()
+	ignored         bool
+	ignoreReason    string
+	shouldPanic     bool
+	expectedPanic   string
+}
+
+type testResult struct {
+	Name     string  `json:"name"`
+	Passed   bool    `json:"passed"`
+	Ignored  bool    `json:"ignored,omitempty"`
+	Duration float64 `json:"duration"`
+	Message  string  `json:"message"`
+}
+
+func runTest(test func(), name string, options testOptions) {
+	switch {
+	case onlyTest != "" && name != onlyTest:
+		return
+	case onlyTest == "" && testsInParallel:
+		parallelTests = append(parallelTests, runTestProcess(name))
+		return
+	}
+
+	if !testsAsJson {
+		fmt.Printf("%s...", name)
+	}
+	if options.ignored && !testsIncludeIgnored {
+		reportTest(testResult{Name: name, Passed: true, Ignored: true, Message: options.ignoreReason})
+		return
+	}
+
+	start := time.Now()
+	passed, message := testOutcome(test, options)
+	reportTest(testResult{
+		Name:     name,
+		Passed:   passed,
+		Duration: time.Since(start).Seconds(),
+		Message:  message,
+	})
+}
+
+func catchPanic(f func()) (panicked bool, message string) {
+	defer func() {
+		if err := recover(); err != nil {
+			panicked, message = true, fmt.Sprint(err)
+		}
+	}()
+	f()
+	return false, ""
+}
+
+// testOutcome runs a test between its module's setup and teardown.
+func testOutcome(test func(), options testOptions) (bool, string) {
+	if options.setup != nil {
+		if panicked, message := catchPanic(options.setup); panicked {
+			return false, "setup failed: " + message
+		}
+	}
+
+	failed, failure := catchPanic(test)
+	if options.shouldPanic {
+		switch {
+		case !failed:
+			failed, failure = true, "test did not panic"
+		case !strings.Contains(failure, options.expectedPanic):
+			failure = fmt.Sprintf("panic did not contain the expected text\n   panic: %q\nexpected: %q", failure, options.expectedPanic)
+		default:
+			failed, failure = false, ""
+		}
+	}
+
+	if options.teardown != nil {
+		if panicked, message := catchPanic(options.teardown); panicked && !failed {
+			failed, failure = true, "teardown failed: " + message
+		}
+	}
+	return !failed, failure
+}
+
+// runTestProcess starts running a test in a process of its own, and returns a
+// function that waits for it to finish and reports its output and result.
+func runTestProcess(name string) func() {
+	done := make(chan []byte, 1)
+	go func() {
+		parallelSlots <- struct{}{}
+		defer func() { <-parallelSlots }()
+		executable, _ := os.Executable()
+		command := exec.Command(executable)
+		command.Env = append(os.Environ(), "FSF_TEST_ONLY="+name, "FSF_TEST_FORMAT=json")
+		output, _ := command.CombinedOutput()
+		done <- output
+	}()
+
+	return func() {
+		output := <-done
+		if !testsAsJson {
+			fmt.Printf("%s...", name)
+		}
+		result := testResult{Name: name, Message: "the test exited without a result"}
+		for _, line := range strings.SplitAfter(string(output), "\n") {
+			if encoded, ok := strings.CutPrefix(line, "##fsf-test "); ok {
+				json.Unmarshal([]byte(encoded), &result)
+			} else {
+				fmt.Print(line)
+			}
+		}
+		reportTest(result)
+	}
+}
+
+func reportTest(result testResult) {
+	switch {
+	case result.Ignored:
+		testsIgnored++
+	case result.Passed:
+		testsPassed++
+	default:
+		testsFailed++
+	}
+
+	switch {
+	case testsAsJson:
+		line, _ := json.Marshal(result)
+		fmt.Printf("##fsf-test %s\n", line)
+	case result.Ignored && result.Message != "":
+		fmt.Printf(" ignored, %s\n", result.Message)
+	case result.Ignored:
+		fmt.Print(" ignored\n")
+	case !result.Passed:
+		fmt.Printf(" fail\n    %s\n", strings.ReplaceAll(result.Message, "\n", "\n    "))
+	default:
+		fmt.Print(" pass\n")
+	}
+}
+
+func reportTests() {
+	for _, report := range parallelTests {
+		report()
+	}
+	if !testsAsJson {
+		fmt.Printf("\n%d passed, %d failed", testsPassed, testsFailed)
+		if testsIgnored > 0 {
+			fmt.Printf(", %d ignored", testsIgnored)
+		}
+		fmt.Print("\n")
+	}
+	if testsFailed > 0 {
+		os.Exit(1)
+	}
+}
@@ -0,0 +1,197 @@
+package main
+
+import (
+	"fmt"
+	"strings"
+)
+
+// htmlNode is an element of the HTML compared by std::testing, or text when it has
+// no tag.
+type htmlNode struct {
+	tag        string
+	attributes [][2]string
+	children   []*htmlNode
+	text       string
+}
+
+var voidElements = map[string]bool{
+	"area": true, "base": true, "br": true, "col": true, "embed": true, "hr": true, "img": true,
+	"input": true, "link": true, "meta": true, "source": true, "track": true, "wbr": true,
+}
+
+// parseHTML parses rendered HTML leniently. Whitespace in text is collapsed and text
+// that is only whitespace is dropped, so formatting doesn't affect comparisons.
+func parseHTML(source string) *htmlNode {
+	root := &htmlNode{}
+	stack := []*htmlNode{root}
+	for len(source) > 0 {
+		parent := stack[len(stack)-1]
+		switch {
+		case strings.HasPrefix(source, "</"):
+			end := strings.IndexByte(source, '>')
+			if end < 0 {
+				end = len(source) - 1
+			}
+			tag := strings.ToLower(strings.TrimSpace(source[2:end]))
+			source = source[end+1:]
+			for i := len(stack) - 1; i > 0; i-- {
+				if stack[i].tag == tag {
+					stack = stack[:i]
+					break
+				}
+			}
+		case source[0] == '<':
+			node, rest, selfClosing := parseStartTag(source[1:])
+			source = rest
+			parent.children = append(parent.children, node)
+			if !selfClosing && !voidElements[node.tag] {
+				stack = append(stack, node)
+			}
+		default:
+			end := strings.IndexByte(source, '<')
+			if end < 0 {
+				end = len(source)
+			}
+			if text := strings.Join(strings.Fields(source[:end]), " "); text != "" {
+				parent.children = append(parent.children, &htmlNode{text: text})
+			}
+			source = source[end:]
+		}
+	}
+	return root
+}
+
+func parseStartTag(source string) (*htmlNode, string, bool) {
+	end := strings.IndexAny(source, " \t\n/>")
+	if end < 0 {
+		end = len(source)
+	}
+	node := &htmlNode{tag: strings.ToLower(source[:end])}
+	source = source[end:]
+	for {
+		source = strings.TrimLeft(source, " \t\r\n")
+		switch {
+		case source == "":
+			return node, source, false
+		case source[0] == '>':
+			return node, source[1:], false
+		case strings.HasPrefix(source, "/>"):
+			return node, source[2:], true
+		case source[0] == '/':
+			source = source[1:]
+			continue
+		}
+
+		end := strings.IndexAny(source, " \t\r\n=/>")
+		if end < 0 {
+			end = len(source)
+		}
+		name := source[:end]
+		source = strings.TrimLeft(source[end:], " \t\r\n")
+		value := ""
+		if strings.HasPrefix(source, "=") {
+			source = strings.TrimLeft(source[1:], " \t\r\n")
+			if source != "" && (source[0] == '"' || source[0] == '\'') {
+				end := strings.IndexByte(source[1:], source[0])
+				if end < 0 {
+					end = len(source) - 1
+				}
+				value = source[1 : end+1]
+				source = source[min(end+2, len(source)):]
+			} else {
+				end := strings.IndexAny(source, " \t\r\n>")
+				if end < 0 {
+					end = len(source)
+				}
+				value = source[:end]
+				source = source[end:]
+			}
+		}
+		node.attributes = append(node.attributes, [2]string{name, value})
+	}
+}
+
+func (node *htmlNode) html() string {
+	if node.tag == "" {
+		html := node.text
+		for _, child := range node.children {
+			html += child.html()
+		}
+		return html
+	}
+
+	var builder strings.Builder
+	builder.WriteString("<" + node.tag)
+	for _, attribute := range node.attributes {
+		builder.WriteString(fmt.Sprintf(` %s="%s"`, attribute[0], attribute[1]))
+	}
+	builder.WriteString(">")
+	if voidElements[node.tag] {
+		return builder.String()
+	}
+	for _, child := range node.children {
+		builder.WriteString(child.html())
+	}
+	builder.WriteString("</" + node.tag + ">")
+	return builder.String()
+}
+
+func (node *htmlNode) textContent() string {
+	if node.tag == "" && node.text != "" {
+		return node.text
+	}
+	texts := []string{}
+	for _, child := range node.children {
+		if text := child.textContent(); text != "" {
+			texts = append(texts, text)
+		}
+	}
+	return strings.Join(texts, " ")
+}
+
+// find returns the innermost element whose text contains text.
+func (node *htmlNode) find(text string) *htmlNode {
+	for _, child := range node.children {
+		if found := child.find(text); found != nil {
+			return found
+		}
+	}
+	if node.tag != "" && strings.Contains(node.textContent(), text) {
+		return node
+	}
+	return nil
+}
+
+func fsfCanonicalHTML(rendered any) string {
+	return parseHTML(fmt.Sprint(rendered)).html()
+}
+
+func fsfAssertHTMLEqual(rendered any, expected string) {
+	actual := fsfCanonicalHTML(rendered)
+	expected = fsfCanonicalHTML(expected)
+	if actual != expected {
+		panic(fmt.Sprintf("assert_html_eq failed\n  actual: %s\nexpected: %s", actual, expected))
+	}
+}
+
+func fsfQueryByText(rendered any, text string) string {
+	if found := parseHTML(fmt.Sprint(rendered)).find(text); found != nil {
+		return found.html()
+	}
+	return ""
+}
+
+// assertEq panics with the failure of an `assert_eq` when its operands differ. The
+// operands are passed in so that each is evaluated once.
+func assertEq[T comparable](failure string, actual, expected T) {
+	if actual != expected {
+		panic(fmt.Sprintf("%s\n  actual: %#v\nexpected: %#v", failure, actual, expected))
+	}
+}
+
+// assertNe panics with the failure of an `assert_ne` when its operands are equal.
+func assertNe[T comparable](failure string, actual, other T) {
+	if actual == other {
+		panic(fmt.Sprintf("%s\n   value: %#v", failure, actual))
+	}
+}
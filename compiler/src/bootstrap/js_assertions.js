@@ -0,0 +1,12 @@
+// Values are equal like they are in Go, where structs and arrays are compared by
+// their contents
+function fsfEqual(left, right) {
+    if (typeof left !== "object" || typeof right !== "object" || left === null || right === null) {
+        return left === right;
+    }
+    const leftKeys = Object.keys(left);
+    const rightKeys = Object.keys(right);
+    return leftKeys.length === rightKeys.length
+        && Object.getPrototypeOf(left) === Object.getPrototypeOf(right)
+        && leftKeys.every((key) => Object.hasOwn(right, key) && fsfEqual(left[key], right[key]));
+}
@@ -0,0 +1,171 @@
+// Programs run by node have no React and no DOM. Elements are plain objects and
+// components render once, the way the server renders them, so hooks return what
+// they return on the first render.
+const React = {
+    createElement: (type, props, ...children) => ({ type, props: { ...props, children } }),
+    useState: (initial) => [initial, () => {}],
+    useEffect: () => {},
+    useMemo: (compute) => compute(),
+    useRef: (current) => ({ current }),
+    createContext: (value) => ({ value }),
+    useContext: (context) => context.value,
+};
+
+const fsfVoidElements = new Set([
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link", "meta", "source", "track", "wbr",
+]);
+
+// Calls the components of an element until only host elements and text are left.
+function fsfRender(element) {
+    if (Array.isArray(element)) {
+        return element.map(fsfRender).flat(Infinity);
+    }
+    if (element === null || typeof element !== "object") {
+        return element;
+    }
+    if (typeof element.type === "function") {
+        return fsfRender(element.type(element.props));
+    }
+    const children = fsfRender(element.props.children ?? []);
+    return { type: element.type, props: { ...element.props, children } };
+}
+
+function fsfEscapeHTML(text) {
+    return text
+        .replaceAll("&", "&amp;")
+        .replaceAll("'", "&#39;")
+        .replaceAll("<", "&lt;")
+        .replaceAll(">", "&gt;")
+        .replaceAll('"', "&#34;");
+}
+
+// Renders an element to the HTML the Go server renders for it.
+function fsfToHTML(element) {
+    const node = fsfRender(element);
+    if (Array.isArray(node)) {
+        return node.map(fsfToHTML).join("");
+    }
+    if (node === null || node === undefined) {
+        return "";
+    }
+    if (typeof node !== "object") {
+        return fsfEscapeHTML(String(node));
+    }
+    const { children, ...props } = node.props;
+    const attributes = Object.entries(props)
+        .filter(([, value]) => ["string", "number", "boolean"].includes(typeof value))
+        .map(([name, value]) => ` ${name}="${fsfEscapeHTML(String(value))}"`)
+        .join("");
+    return `<${node.type}${attributes}>${fsfToHTML(children)}</${node.type}>`;
+}
+
+// Parses HTML leniently. Whitespace in text is collapsed and text that is only
+// whitespace is dropped, so formatting doesn't affect comparisons.
+function fsfParseHTML(source) {
+    const root = { tag: "", attributes: [], children: [] };
+    const stack = [root];
+    let i = 0;
+    while (i < source.length) {
+        const parent = stack[stack.length - 1];
+        if (source.startsWith("</", i)) {
+            let end = source.indexOf(">", i);
+            if (end < 0) {
+                end = source.length;
+            }
+            const tag = source.slice(i + 2, end).trim().toLowerCase();
+            i = end + 1;
+            const open = stack.findLastIndex((node) => node.tag === tag);
+            if (open > 0) {
+                stack.length = open;
+            }
+        } else if (source[i] === "<") {
+            const tag = /^<([^\s/>]*)/.exec(source.slice(i));
+            const node = { tag: tag[1].toLowerCase(), attributes: [], children: [] };
+            i += tag[0].length;
+            const attribute = /^\s*(?:(\/?>)|\/|([^\s=/>]+)(?:\s*=\s*(?:"([^"]*)"?|'([^']*)'?|([^\s>]*)))?)/;
+            let match;
+            while (i < source.length && (match = attribute.exec(source.slice(i)))) {
+                i += match[0].length;
+                if (match[1]) {
+                    break;
+                }
+                if (match[2]) {
+                    node.attributes.push([match[2], match[3] ?? match[4] ?? match[5] ?? ""]);
+                }
+            }
+            parent.children.push(node);
+            if (match?.[1] === ">" && !fsfVoidElements.has(node.tag)) {
+                stack.push(node);
+            }
+        } else {
+            let end = source.indexOf("<", i);
+            if (end < 0) {
+                end = source.length;
+            }
+            const text = source.slice(i, end).split(/\s+/).filter(Boolean).join(" ");
+            if (text) {
+                parent.children.push({ text });
+            }
+            i = end;
+        }
+    }
+    return root;
+}
+
+function fsfHTMLOf(node) {
+    if (node.text !== undefined) {
+        return node.text;
+    }
+    const children = node.children.map(fsfHTMLOf).join("");
+    if (!node.tag) {
+        return children;
+    }
+    const attributes = node.attributes.map(([name, value]) => ` ${name}="${value}"`).join("");
+    if (fsfVoidElements.has(node.tag)) {
+        return `<${node.tag}${attributes}>`;
+    }
+    return `<${node.tag}${attributes}>${children}</${node.tag}>`;
+}
+
+function fsfTextContent(node) {
+    if (node.text !== undefined) {
+        return node.text;
+    }
+    return node.children.map(fsfTextContent).filter(Boolean).join(" ");
+}
+
+// The innermost element whose text contains text.
+function fsfFind(node, text) {
+    for (const child of node.children ?? []) {
+        const found = fsfFind(child, text);
+        if (found) {
+            return found;
+        }
+    }
+    if (node.tag && fsfTextContent(node).includes(text)) {
+        return node;
+    }
+    return null;
+}
+
+function fsfParseRendered(rendered) {
+    return fsfParseHTML(typeof rendered === "string" ? rendered : fsfToHTML(rendered));
+}
+
+function fsfCanonicalHTML(rendered) {
+    return fsfHTMLOf(fsfParseRendered(rendered));
+}
+
+function fsfAssertHTMLEqual(rendered, expected) {
+    const actual = fsfCanonicalHTML(rendered);
+    expected = fsfCanonicalHTML(expected);
+    if (actual !== expected) {
+        throw new Error(`assert_html_eq failed\n  actual: ${actual}\nexpected: ${expected}`);
+    }
+}
+
+function fsfQueryByText(rendered, text) {
+    const found = fsfFind(fsfParseRendered(rendered), text);
+    return found ? fsfHTMLOf(found) : "";
+}
+
@@ -0,0 +1,75 @@
+
+// fsf numbers behave the same in JavaScript as in Go. An int is a number kept in 32
+// bits, an i64 is a BigInt kept in 64 bits, and both wrap around when they overflow.
+// Integer division truncates and panics when dividing by zero.
+function fsfDivideInt(left, right) {
+    if (right === 0) {
+        throw new Error("runtime error: integer divide by zero");
+    }
+    return (left / right) | 0;
+}
+
+function fsfDivideI64(left, right) {
+    if (right === 0n) {
+        throw new Error("runtime error: integer divide by zero");
+    }
+    return BigInt.asIntN(64, left / right);
+}
+
+// Conversions of values whose type isn't known until they're run
+function fsfToInt(value) {
+    return typeof value === "bigint" ? Number(BigInt.asIntN(32, value)) : value | 0;
+}
+
+function fsfToI64(value) {
+    return typeof value === "bigint" ? value : BigInt(Math.trunc(value));
+}
+
+function fsfToFloat(value) {
+    return Number(value);
+}
+
+function fsfPrint(value) {
+    console.log(typeof value === "bigint" ? value.toString() : value);
+}
+
+// Formats a value for an assertion failure like JSON, with i64 values written as
+// numbers rather than the strings they're sent as
+function fsfFormat(value) {
+    const text = JSON.stringify(value, function (key, json) {
+        return typeof this[key] === "bigint" ? `\u0000${this[key]}` : json;
+    });
+    return text === undefined ? String(value) : text.replace(/"\\u0000(-?\d+)"/g, "$1");
+}
+
+BigInt.prototype.toJSON = function () {
+    return this.toString();
+};
+
+// Formats a float like Go's %.Nf, which rounds a value exactly halfway between two
+// results to the even one where toFixed rounds it away from zero
+function fsfToFixed(value, precision) {
+    const fixed = value.toFixed(precision);
+    if (!Number.isFinite(value) || Math.abs(value) >= 1e21 || precision >= 100) {
+        return fixed;
+    }
+    const exact = Math.abs(value).toFixed(100);
+    const point = exact.indexOf(".");
+    const kept = exact.slice(0, precision > 0 ? point + precision + 1 : point);
+    const isHalfway = /^50*$/.test(exact.slice(point + precision + 1));
+    if (isHalfway && Number(kept[kept.length - 1]) % 2 === 0) {
+        return (value < 0 ? "-" : "") + kept;
+    }
+    return fixed;
+}
+
+// Pads text to a width in characters like Go's fmt, with zeros after the sign of a
+// number or with spaces
+function fsfPad(text, width, alignLeft, zero) {
+    const padding = Math.max(0, width - [...text].length);
+    if (zero) {
+        const sign = text.startsWith("-") ? "-" : "";
+        return sign + "0".repeat(padding) + text.slice(sign.length);
+    }
+    return alignLeft ? text + " ".repeat(padding) : " ".repeat(padding) + text;
+}
@@ -0,0 +1,96 @@
+let testsPassed = 0;
+let testsFailed = 0;
+let testsIgnored = 0;
+
+// fsf sets FSF_TEST_FORMAT=json to read each result as a line of JSON
+const testsAsJson = process.env.FSF_TEST_FORMAT === "json";
+const testsIncludeIgnored = Boolean(process.env.FSF_TEST_INCLUDE_IGNORED);
+
+function runTest(test, name, options) {
+    if (!testsAsJson) {
+        process.stdout.write(`${name}...`);
+    }
+    if (options.ignoreReason !== undefined && !testsIncludeIgnored) {
+        reportTest({ name, passed: true, ignored: true, duration: 0, message: options.ignoreReason });
+        return;
+    }
+
+    const start = performance.now();
+    const failure = testFailure(test, options);
+    reportTest({
+        name,
+        passed: failure === null,
+        duration: (performance.now() - start) / 1000,
+        message: failure ?? "",
+    });
+}
+
+function catchPanic(f) {
+    try {
+        f();
+        return null;
+    } catch (err) {
+        return String(err instanceof Error ? err.message : err);
+    }
+}
+
+// Runs a test between its module's setup and teardown, returning why it
+// failed or null if it passed.
+function testFailure(test, options) {
+    if (options.setup) {
+        const message = catchPanic(options.setup);
+        if (message !== null) {
+            return `setup failed: ${message}`;
+        }
+    }
+
+    const panic = catchPanic(test);
+    let failure = panic;
+    if (options.expectedPanic !== undefined) {
+        if (panic === null) {
+            failure = "test did not panic";
+        } else if (!panic.includes(options.expectedPanic)) {
+            failure = `panic did not contain the expected text\n   panic: ${JSON.stringify(panic)}\nexpected: ${JSON.stringify(options.expectedPanic)}`;
+        } else {
+            failure = null;
+        }
+    }
+
+    if (options.teardown) {
+        const message = catchPanic(options.teardown);
+        if (message !== null && failure === null) {
+            failure = `teardown failed: ${message}`;
+        }
+    }
+    return failure;
+}
+
+function reportTest(result) {
+    if (result.ignored) {
+        testsIgnored++;
+    } else if (result.passed) {
+        testsPassed++;
+    } else {
+        testsFailed++;
+    }
+
+    if (testsAsJson) {
+        process.stdout.write(`##fsf-test ${JSON.stringify(result)}\n`);
+    } else if (result.ignored) {
+        process.stdout.write(result.message ? ` ignored, ${result.message}\n` : " ignored\n");
+    } else if (!result.passed) {
+        process.stdout.write(` fail\n    ${result.message.replaceAll("\n", "\n    ")}\n`);
+    } else {
+        process.stdout.write(" pass\n");
+    }
+}
+
+function reportTests() {
+    if (!testsAsJson) {
+        const ignored = testsIgnored > 0 ? `, ${testsIgnored} ignored` : "";
+        process.stdout.write(`\n${testsPassed} passed, ${testsFailed} failed${ignored}\n`);
+    }
+    if (testsFailed > 0) {
+        process.exitCode = 1;
+    }
+}
@@ -1,6 +1,16 @@
-if (typeof window !== 'undefined') {
+import {hydrateRoot} from 'react-dom/client';
+
+// Hydrates the server rendered route, called from the entry point of each route
+export async function hydrate() {
+    if (typeof window === 'undefined') {
+        return;
+    }
     const container = document.getElementById('root');
-    if (container) {
-        hydrateRoot(container, <Index/>);
+    const initialData = window.__INITIAL_DATA__ ?? {};
+    const match = matchRoute(window.location.pathname);
+    if (container && match) {
+        const component = await match.route.load();
+        fsfRoot = hydrateRoot(container, renderRoute(component, match.route, match.params, initialData.data));
+        window.addEventListener('popstate', () => fsfNavigate(window.location.href, false));
     }
-}
\ No newline at end of file
+}
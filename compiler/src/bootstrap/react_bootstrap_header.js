@@ -1,2 +1 @@
 import React from 'react';
-import {hydrateRoot} from 'react-dom/client';
\ No newline at end of file
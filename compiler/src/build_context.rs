@@ -0,0 +1,95 @@
+use anyhow::anyhow;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// Marks a directory as created by the compiler, so that it is safe to clean.
+const MARKER_FILE: &str = ".fsf-build";
+
+/// The directory a build writes its output to. Toolchain commands run inside it
+/// instead of changing the compiler's working directory, and cleaning a build
+/// only ever removes a directory the compiler created itself.
+pub struct BuildContext {
+    out_dir: PathBuf,
+}
+
+impl BuildContext {
+    /// Builds into `out_dir`, or `.fsf/{name}` inside the project when it isn't
+    /// given, so that different commands on the same project don't clobber each other.
+    pub fn new(project: &Path, name: &str, out_dir: Option<PathBuf>) -> Self {
+        Self {
+            out_dir: out_dir.unwrap_or(project.join(".fsf").join(name)),
+        }
+    }
+
+    pub fn out_dir(&self) -> &Path {
+        &self.out_dir
+    }
+
+    /// The npm package the JavaScript is bundled in, which the Go server embeds.
+    pub fn js_dir(&self) -> PathBuf {
+        self.out_dir.join("javascript")
+    }
+
+    /// Removes the output of a previous build and creates an empty output directory.
+    pub fn clean(&self) -> Result<()> {
+        if self.out_dir.exists() {
+            let is_empty = std::fs::read_dir(&self.out_dir)?.next().is_none();
+            if !is_empty && !self.out_dir.join(MARKER_FILE).exists() {
+                return Err(anyhow!(
+                    "Refusing to clean {} as it wasn't created by fsf",
+                    self.out_dir.display()
+                ));
+            }
+            std::fs::remove_dir_all(&self.out_dir)?;
+        }
+        std::fs::create_dir_all(&self.out_dir)?;
+        std::fs::write(self.out_dir.join(MARKER_FILE), "")?;
+        Ok(())
+    }
+
+    /// Removes the Go files and JavaScript modules of a previous build into the same
+    /// directory, so that the ones of modules that were since removed or renamed aren't
+    /// built along with the others. Installed dependencies and the bundle are kept.
+    pub fn remove_generated(&self) -> Result<()> {
+        for entry in std::fs::read_dir(&self.out_dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|extension| extension == "go") {
+                std::fs::remove_file(path)?;
+            }
+        }
+        for dir in ["entries", "modules"] {
+            let dir = self.js_dir().join(dir);
+            if dir.exists() {
+                std::fs::remove_dir_all(dir)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// A command that runs in the output directory.
+    pub fn command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        command.current_dir(&self.out_dir);
+        command
+    }
+
+    /// A command that runs in the JavaScript package.
+    pub fn js_command(&self, program: &str) -> Command {
+        let mut command = Command::new(program);
+        command.current_dir(self.js_dir());
+        command
+    }
+
+    /// Makes the output directory a Go module so it can be run and built.
+    pub fn init_go_module(&self) -> Result<()> {
+        // This fails when the runtime's go.mod was already copied, which is fine
+        let _ = self
+            .command("go")
+            .arg("mod")
+            .arg("init")
+            .arg("fsf")
+            .output()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,191 @@
+use crate::build_context::BuildContext;
+use crate::targets::source_map::SourceMaps;
+use crate::targets::{Module, Program};
+use crate::{copy_public_assets, fsf_files, generate_web, parse_module_from_file, run_toolchain};
+use anyhow::anyhow;
+use anyhow::Result;
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
+use std::time::{Duration, Instant, SystemTime};
+
+/// How often the project is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// The name of the server binary the dev server builds and restarts.
+const SERVER_BINARY: &str = "fsf-dev-server";
+
+/// Serves a project and rebuilds it whenever one of its modules changes. Only the
+/// modules that changed are parsed again, the JavaScript bundle is rebuilt
+/// incrementally by a long running esbuild process and connected browsers reload
+/// once the Go server has restarted.
+pub struct DevServer {
+    path: PathBuf,
+    context: BuildContext,
+    /// The modules by the time they were modified, or `None` when they don't parse.
+    modules: HashMap<PathBuf, (SystemTime, Option<Module>)>,
+    bundler: Option<Bundler>,
+    server: Option<Child>,
+}
+
+/// The esbuild process started by `build.js --watch`, which rebuilds the bundle
+/// every time it reads a line.
+struct Bundler {
+    process: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+}
+
+impl DevServer {
+    pub fn new(path: PathBuf, context: BuildContext) -> Self {
+        Self {
+            path,
+            context,
+            modules: HashMap::new(),
+            bundler: None,
+            server: None,
+        }
+    }
+
+    pub fn watch(&mut self) -> Result<()> {
+        println!("Watching {} for changes", self.path.display());
+        loop {
+            // The previous server keeps running until the project builds again
+            if self.refresh_modules()? {
+                let start = Instant::now();
+                match self.rebuild() {
+                    Ok(()) => println!("Rebuilt in {}ms", start.elapsed().as_millis()),
+                    Err(error) => eprintln!("Build failed: {}", error),
+                }
+            }
+            std::thread::sleep(POLL_INTERVAL);
+        }
+    }
+
+    /// Parses the modules that were added or modified since the last refresh and
+    /// returns whether anything changed. Modules that don't parse are reported once,
+    /// until they are modified again.
+    fn refresh_modules(&mut self) -> Result<bool> {
+        let mut changed = false;
+        let mut paths = Vec::new();
+        for path in fsf_files(&self.path) {
+            let modified = path.metadata()?.modified()?;
+            if !matches!(self.modules.get(&path), Some((m, _)) if *m == modified) {
+                let module = parse_module_from_file(path.clone())
+                    .inspect_err(|error| eprintln!("{}", error))
+                    .ok();
+                self.modules.insert(path.clone(), (modified, module));
+                changed = true;
+            }
+            paths.push(path);
+        }
+
+        let module_count = self.modules.len();
+        self.modules.retain(|path, _| paths.contains(path));
+        Ok(changed || self.modules.len() != module_count)
+    }
+
+    fn rebuild(&mut self) -> Result<()> {
+        let mut program = self
+            .modules
+            .values()
+            .map(|(_, module)| module.clone())
+            .collect::<Option<Program>>()
+            .ok_or(anyhow!("Some modules don't parse"))?;
+        program.sort_by(|a, b| a.path.cmp(&b.path));
+
+        self.context.remove_generated()?;
+        let source_maps = generate_web(&self.path, program, &self.context)?;
+        self.bundle()?;
+        copy_public_assets(&self.path, &self.context)?;
+        self.restart_server(&source_maps)
+    }
+
+    fn bundle(&mut self) -> Result<()> {
+        if self.bundler.is_none() {
+            self.bundler = Some(self.start_bundler()?);
+        }
+
+        let bundler = self.bundler.as_mut().unwrap();
+        writeln!(bundler.stdin)?;
+        let mut response = String::new();
+        bundler.stdout.read_line(&mut response)?;
+        match response.trim() {
+            "rebuilt" => Ok(()),
+            "failed" => Err(anyhow!("Failed to bundle JavaScript")),
+            _ => {
+                // The bundler exited, so start a new one on the next change
+                self.bundler = None;
+                Err(anyhow!("JavaScript bundler exited unexpectedly"))
+            }
+        }
+    }
+
+    fn start_bundler(&self) -> Result<Bundler> {
+        if !self
+            .context
+            .js_command("npm")
+            .arg("install")
+            .status()?
+            .success()
+        {
+            return Err(anyhow!("Failed to install JavaScript dependencies"));
+        }
+
+        let mut process = self
+            .context
+            .js_command("node")
+            .arg("build.js")
+            .arg("--watch")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()?;
+        Ok(Bundler {
+            stdin: process.stdin.take().unwrap(),
+            stdout: BufReader::new(process.stdout.take().unwrap()),
+            process,
+        })
+    }
+
+    fn restart_server(&mut self, source_maps: &SourceMaps) -> Result<()> {
+        self.context.init_go_module()?;
+        if !run_toolchain(
+            self.context
+                .command("go")
+                .arg("build")
+                .arg("-o")
+                .arg(SERVER_BINARY),
+            source_maps,
+        )? {
+            return Err(anyhow!("Failed to build Go server"));
+        }
+
+        if let Some(mut server) = self.server.take() {
+            server.kill()?;
+            server.wait()?;
+        }
+        self.server = Some(
+            Command::new(self.context.out_dir().canonicalize()?.join(SERVER_BINARY))
+                .current_dir(self.context.out_dir())
+                .env("FSF_LIVE_RELOAD", "1")
+                .spawn()?,
+        );
+        Ok(())
+    }
+}
+
+/// The server and the bundler are stopped along with the dev server, including when it
+/// stops because of an error.
+impl Drop for DevServer {
+    fn drop(&mut self) {
+        if let Some(mut server) = self.server.take() {
+            let _ = server.kill();
+            let _ = server.wait();
+        }
+        if let Some(mut bundler) = self.bundler.take() {
+            let _ = bundler.process.kill();
+            let _ = bundler.process.wait();
+        }
+    }
+}
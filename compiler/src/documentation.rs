@@ -0,0 +1,344 @@
+use crate::analysis::signature;
+use crate::item::{Attribute, Item};
+use crate::targets::{Module, Program};
+use anyhow::Result;
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::path::Path;
+
+const STYLESHEET: &str = include_str!("templates/doc.css");
+
+/// The sections of a module's page, by the kind of item they list.
+const SECTIONS: [(&str, &str); 4] = [
+    ("struct", "Structs"),
+    ("cmpnt", "Components"),
+    ("loader", "Loaders"),
+    ("fn", "Functions"),
+];
+
+/// A module and the page it is documented on.
+struct Page<'a> {
+    /// The path the module is imported by, like `std::testing`.
+    name: String,
+    /// Where the page is written, relative to the root of the documentation.
+    file: String,
+    module: &'a Module,
+}
+
+impl Page<'_> {
+    /// The link from the page back to the root of the documentation.
+    fn root(&self) -> String {
+        "../".repeat(self.file.matches('/').count())
+    }
+
+    fn is_std(&self) -> bool {
+        self.name.starts_with("std::")
+    }
+}
+
+/// Writes the documentation of a project's modules to `out_dir` as HTML, with a page for
+/// every module and an index of them all. The standard library is documented along with
+/// the project when its modules are in `program`, as if they were in a `std` directory.
+pub fn generate(project: &Path, program: &Program, out_dir: &Path) -> Result<()> {
+    let title = project
+        .canonicalize()?
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or("fsf".to_string());
+
+    let mut pages = Vec::new();
+    for module in program {
+        let relative = module.path.strip_prefix(project)?.with_extension("");
+        let segments = relative.iter().map(|s| s.to_string_lossy()).collect_vec();
+        pages.push(Page {
+            name: segments.join("::"),
+            file: format!("{}.html", segments.join("/")),
+            module,
+        });
+    }
+    // The project's modules come before the standard library's
+    pages.sort_by(|a, b| (a.is_std(), &a.name).cmp(&(b.is_std(), &b.name)));
+
+    // Struct names in signatures link to the struct, which is looked for in the same
+    // module first and then in the order of the pages
+    let mut structs = HashMap::new();
+    for page in pages.iter().rev() {
+        for item in &page.module.items {
+            if let Item::Struct { name, .. } = item {
+                structs.insert(name.as_str(), format!("{}#struct.{}", page.file, name));
+            }
+        }
+    }
+
+    std::fs::write(out_dir.join("style.css"), STYLESHEET)?;
+    std::fs::write(out_dir.join("index.html"), index_page(&title, &pages))?;
+    for page in &pages {
+        let file = out_dir.join(&page.file);
+        if let Some(dir) = file.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(file, module_page(&title, page, &structs))?;
+    }
+    Ok(())
+}
+
+/// The kind and name of an item that is documented. Tests are left out.
+fn documented(item: &Item) -> Option<(&'static str, &str)> {
+    match item {
+        Item::Function {
+            name, attributes, ..
+        } if name.starts_with("test_") || Attribute::find(attributes, "test").is_some() => None,
+        Item::Function { name, .. } | Item::Action { name, .. } => Some(("fn", name)),
+        Item::Component { name, .. } => Some(("cmpnt", name)),
+        Item::Loader { name, .. } => Some(("loader", name)),
+        Item::Struct { name, .. } => Some(("struct", name)),
+        Item::Import { .. } | Item::TestRunner | Item::Router { .. } => None,
+    }
+}
+
+fn index_page(title: &str, pages: &[Page]) -> String {
+    let mut body = format!("<h1>{}</h1>\n", escape_html(title));
+    for (is_std, pages) in &pages.iter().chunk_by(|page| page.is_std()) {
+        body.push_str(match is_std {
+            true => "<h2>Standard library</h2>\n",
+            false => "<h2>Modules</h2>\n",
+        });
+        for page in pages {
+            body.push_str(&format!(
+                "<h3><a href=\"{}\">{}</a></h3>\n",
+                page.file,
+                escape_html(&page.name)
+            ));
+            let rows = page
+                .module
+                .items
+                .iter()
+                .filter_map(|item| Some((documented(item)?, item.doc())))
+                .map(|((kind, name), doc)| {
+                    format!(
+                        "<tr><td><a href=\"{}#{}.{}\"><code>{}</code></a></td><td>{}</td></tr>\n",
+                        page.file,
+                        kind,
+                        name,
+                        name,
+                        doc.map(summary).unwrap_or_default()
+                    )
+                })
+                .join("");
+            if !rows.is_empty() {
+                body.push_str(&format!("<table>\n{}</table>\n", rows));
+            }
+        }
+    }
+    html_page(title, "", title, &body)
+}
+
+fn module_page(title: &str, page: &Page, structs: &HashMap<&str, String>) -> String {
+    let root = page.root();
+    let mut links = structs
+        .iter()
+        .map(|(name, href)| (*name, format!("{}{}", root, href)))
+        .collect::<HashMap<_, _>>();
+    for item in &page.module.items {
+        if let Item::Struct { name, .. } = item {
+            links.insert(name, format!("#struct.{}", name));
+        }
+    }
+
+    let mut body = format!("<h1>Module <code>{}</code></h1>\n", escape_html(&page.name));
+    for (section_kind, heading) in SECTIONS {
+        let items = page
+            .module
+            .items
+            .iter()
+            .filter_map(|item| match documented(item) {
+                Some((kind, name)) if kind == section_kind => {
+                    Some(item_section(item, name, &links))
+                }
+                _ => None,
+            })
+            .join("");
+        if !items.is_empty() {
+            body.push_str(&format!("<h2>{}</h2>\n{}", heading, items));
+        }
+    }
+    html_page(&format!("{} - {}", page.name, title), &root, title, &body)
+}
+
+fn item_section(item: &Item, name: &str, links: &HashMap<&str, String>) -> String {
+    let (kind, _) = documented(item).unwrap();
+    let declaration = match item {
+        Item::Struct { .. } => format!("struct {}", name),
+        _ => signature(item, name).unwrap_or_default(),
+    };
+    let mut html = format!(
+        "<section class=\"item\" id=\"{}.{}\">\n<pre class=\"signature\">{}</pre>\n",
+        kind,
+        name,
+        link_types(&declaration, links)
+    );
+    if let Some(doc) = item.doc() {
+        html.push_str(&render_doc(doc));
+    }
+    match item {
+        Item::Struct { fields, .. } if !fields.is_empty() => html.push_str(&table(
+            "Fields",
+            fields.iter().map(|field| {
+                (
+                    field.name.as_str(),
+                    field.type_annotation.as_str(),
+                    field.doc.as_deref(),
+                )
+            }),
+            links,
+        )),
+        Item::Component { parameters, .. } if !parameters.is_empty() => html.push_str(&table(
+            "Props",
+            parameters.iter().map(|parameter| {
+                (
+                    parameter.name.as_str(),
+                    parameter.type_annotation.as_str(),
+                    None,
+                )
+            }),
+            links,
+        )),
+        _ => {}
+    }
+    html.push_str("</section>\n");
+    html
+}
+
+/// A table of names and their types, like the fields of a struct, with a column for
+/// their doc comments when any of them has one.
+fn table<'a>(
+    heading: &str,
+    rows: impl Iterator<Item = (&'a str, &'a str, Option<&'a str>)>,
+    links: &HashMap<&str, String>,
+) -> String {
+    let rows = rows.collect_vec();
+    let has_docs = rows.iter().any(|(_, _, doc)| doc.is_some());
+    let rows = rows
+        .into_iter()
+        .map(|(name, type_annotation, doc)| {
+            let doc = match has_docs {
+                true => format!("<td>{}</td>", doc.map(render_doc).unwrap_or_default()),
+                false => String::new(),
+            };
+            format!(
+                "<tr><td><code>{}</code></td><td><code>{}</code></td>{}</tr>\n",
+                escape_html(name),
+                link_types(type_annotation, links),
+                doc
+            )
+        })
+        .join("");
+    format!("<h4>{}</h4>\n<table>\n{}</table>\n", heading, rows)
+}
+
+fn html_page(title: &str, root: &str, home: &str, body: &str) -> String {
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n<link rel=\"stylesheet\" href=\"{}style.css\">\n</head>\n<body>\n<nav><a href=\"{}index.html\">{}</a></nav>\n<main>\n{}</main>\n</body>\n</html>\n",
+        escape_html(title),
+        root,
+        root,
+        escape_html(home),
+        body
+    )
+}
+
+/// Escapes code, linking the names of structs to their documentation.
+fn link_types(code: &str, links: &HashMap<&str, String>) -> String {
+    let is_word = |c: char| c.is_alphanumeric() || c == '_';
+    let mut html = String::new();
+    let mut rest = code;
+    while let Some(start) = rest.find(is_word) {
+        html.push_str(&escape_html(&rest[..start]));
+        rest = &rest[start..];
+        let end = rest.find(|c| !is_word(c)).unwrap_or(rest.len());
+        let word = &rest[..end];
+        match links.get(word) {
+            Some(href) => html.push_str(&format!("<a href=\"{}\">{}</a>", href, word)),
+            None => html.push_str(word),
+        }
+        rest = &rest[end..];
+    }
+    html.push_str(&escape_html(rest));
+    html
+}
+
+/// Renders the text of a doc comment. Paragraphs are separated by blank lines, text in
+/// backticks is code, and fenced code blocks are kept as they are written.
+fn render_doc(doc: &str) -> String {
+    let mut html = String::new();
+    let mut paragraph = Vec::new();
+    let mut code_block: Option<Vec<&str>> = None;
+    for line in doc.lines() {
+        let is_fence = line.trim_start().starts_with("```");
+        match &mut code_block {
+            Some(code) if is_fence => {
+                html.push_str(&code_block_html(code));
+                code_block = None;
+            }
+            Some(code) => code.push(line),
+            None if is_fence => {
+                html.push_str(&paragraph_html(&paragraph));
+                paragraph.clear();
+                code_block = Some(Vec::new());
+            }
+            None if line.trim().is_empty() => {
+                html.push_str(&paragraph_html(&paragraph));
+                paragraph.clear();
+            }
+            None => paragraph.push(line.trim()),
+        }
+    }
+    // A code block that isn't closed runs to the end of the comment
+    if let Some(code) = code_block {
+        html.push_str(&code_block_html(&code));
+    }
+    html.push_str(&paragraph_html(&paragraph));
+    html
+}
+
+/// The first paragraph of a doc comment, for lists of items.
+fn summary(doc: &str) -> String {
+    let paragraph = doc
+        .lines()
+        .map(str::trim)
+        .take_while(|line| !line.is_empty() && !line.starts_with("```"))
+        .join(" ");
+    inline_html(&paragraph)
+}
+
+fn paragraph_html(lines: &[&str]) -> String {
+    match lines.is_empty() {
+        true => String::new(),
+        false => format!("<p>{}</p>\n", inline_html(&lines.join(" "))),
+    }
+}
+
+fn code_block_html(lines: &[&str]) -> String {
+    format!(
+        "<pre><code>{}</code></pre>\n",
+        escape_html(&lines.join("\n"))
+    )
+}
+
+/// Escapes text, setting the parts of it in backticks as code.
+fn inline_html(text: &str) -> String {
+    text.split('`')
+        .enumerate()
+        .map(|(i, part)| match i % 2 {
+            1 => format!("<code>{}</code>", escape_html(part)),
+            _ => escape_html(part),
+        })
+        .join("")
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
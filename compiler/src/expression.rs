@@ -1,10 +1,35 @@
 use crate::statement::Statement;
-use crate::token::{Literal, Token};
+use crate::token::{Literal, NumberType, Token};
 
 #[derive(Debug, Clone)]
 pub enum FStringChunk {
     Literal(String),
-    Identifier(String),
+    /// An expression between braces, like `{total:.2}`.
+    Expression {
+        expression: Box<Expression>,
+        format: FormatSpec,
+        /// The type of the expression when it's a number, filled in by the
+        /// `NumberTransformer`.
+        number_type: Option<NumberType>,
+    },
+}
+
+/// How an f-string expression is formatted, from what follows the `:` in `{x:>8.2}`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FormatSpec {
+    /// Numbers are aligned right by default and everything else left.
+    pub align: Option<Align>,
+    /// Pads a number with zeros after its sign rather than with spaces, like `{x:08}`.
+    pub zero: bool,
+    pub width: Option<usize>,
+    /// The number of digits after the decimal point of a float.
+    pub precision: Option<usize>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Align {
+    Left,
+    Right,
 }
 
 #[derive(Debug, Clone)]
@@ -21,10 +46,13 @@ pub struct LambdaParameter {
 
 #[derive(Debug, Clone)]
 pub enum ExpressionWithoutBlock {
+    /// Operations on numbers have the type of their operands, which the
+    /// `NumberTransformer` fills in.
     Binary {
         left: Box<ExpressionWithoutBlock>,
         operator: Token,
         right: Box<ExpressionWithoutBlock>,
+        number_type: Option<NumberType>,
     },
     Call {
         callee: Box<ExpressionWithoutBlock>,
@@ -47,12 +75,14 @@ pub enum ExpressionWithoutBlock {
     Unary {
         operator: Token,
         right: Box<ExpressionWithoutBlock>,
+        number_type: Option<NumberType>,
     },
     Variable(Token),
     Assignment {
         name: Token,
         value: Box<ExpressionWithoutBlock>,
         operator: Token,
+        number_type: Option<NumberType>,
     },
     Html {
         name: Token,
@@ -64,6 +94,7 @@ pub enum ExpressionWithoutBlock {
     },
     Array {
         elements: Vec<Expression>,
+        element_type: Option<NumberType>,
     },
     Tuple {
         elements: Vec<Expression>,
@@ -72,6 +103,13 @@ pub enum ExpressionWithoutBlock {
         name: Token,
         fields: Vec<(Token, Expression)>,
     },
+    /// A conversion between number types, like `float(count)`. The type converted from
+    /// isn't known when the expression isn't a number.
+    Convert {
+        expression: Box<Expression>,
+        from: Option<NumberType>,
+        to: NumberType,
+    },
     RawJs(String),
     RawGo(String),
 }
@@ -88,8 +126,11 @@ pub enum ExpressionWithBlock {
 
 #[derive(Debug, Clone)]
 pub struct BlockExpression {
-    pub statements: Vec<Statement>,
+    /// Each statement is paired with the source line it starts on
+    pub statements: Vec<(usize, Statement)>,
     pub expr: Option<ExpressionWithoutBlock>,
+    /// The source line of the opening brace
+    pub line: usize,
 }
 
 impl From<ExpressionWithoutBlock> for Expression {
@@ -0,0 +1,478 @@
+use crate::parse_module;
+use crate::scanner::Scanner;
+use crate::token::{Token, TokenType};
+use anyhow::Result;
+use std::path::Path;
+
+const INDENT: &str = "    ";
+
+/// Formats a fsf module into the canonical style. The module is parsed first so
+/// that only valid code is formatted, then its tokens are printed back out along
+/// with its comments, which the parser never sees.
+pub fn format_source(source: &str, path: &Path) -> Result<String> {
+    parse_module(source.to_string(), path.to_path_buf())?;
+
+    let tokens = Scanner::with_comments(source.to_string()).scan_tokens();
+    let mut formatter = Formatter::new(&tokens);
+    formatter.format();
+    Ok(formatter.output)
+}
+
+/// The constructs a token can be nested in, which decide how the tokens inside
+/// them are laid out.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Scope {
+    /// Statements, one per line.
+    Block,
+    /// Fields, one per line with a trailing comma.
+    StructLiteral,
+    StructDefinition,
+    /// Braces kept on a single line, like attribute values.
+    Inline,
+    Paren,
+    Bracket,
+    /// Between `<name` and `>`.
+    OpenTag,
+    /// The children of an element, which are only indented when they include
+    /// other elements.
+    Tag {
+        inline: bool,
+    },
+}
+
+/// What a token means in its context, for the tokens that are ambiguous on their own.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Role {
+    Plain,
+    BlockEnd,
+    HtmlOpen,
+    TagEnd,
+    SelfClosingTagEnd,
+    ClosingTag,
+    ClosingTagEnd,
+    LambdaOpen,
+    LambdaClose,
+    Unary,
+    TypeParametersOpen,
+    TypeParametersClose,
+}
+
+/// The whitespace printed before a token.
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum Separator {
+    None,
+    Space,
+    Newline,
+    BlankLine,
+}
+
+struct Formatter<'a> {
+    tokens: &'a [Token],
+    roles: Vec<Role>,
+    scopes: Vec<Scope>,
+    output: String,
+
+    // Where the next `{` opens a block rather than a struct literal
+    in_signature: bool,
+    if_condition: Option<usize>,
+    struct_definition: bool,
+
+    in_type_parameters: bool,
+    in_lambda_parameters: bool,
+    in_closing_tag: bool,
+}
+
+impl<'a> Formatter<'a> {
+    fn new(tokens: &'a [Token]) -> Self {
+        Self {
+            tokens,
+            roles: Vec::new(),
+            scopes: Vec::new(),
+            output: String::new(),
+            in_signature: false,
+            if_condition: None,
+            struct_definition: false,
+            in_type_parameters: false,
+            in_lambda_parameters: false,
+            in_closing_tag: false,
+        }
+    }
+
+    fn format(&mut self) {
+        for i in 0..self.tokens.len() {
+            let token = &self.tokens[i];
+            if token.token_type == TokenType::Eof {
+                break;
+            }
+
+            let closed = match token.token_type {
+                TokenType::RightBrace
+                | TokenType::RightParen
+                | TokenType::RightSquareBracket
+                | TokenType::LessSlash => self.scopes.pop(),
+                _ => None,
+            };
+            let role = self.role(i, closed);
+
+            if i > 0 {
+                if closed == Some(Scope::StructLiteral)
+                    && !matches!(
+                        self.tokens[i - 1].token_type,
+                        TokenType::Comma | TokenType::LeftBrace | TokenType::Comment
+                    )
+                {
+                    self.output.push(',');
+                }
+                let separator = self.separator(i, role, closed);
+                self.write_separator(separator);
+            }
+            self.output.push_str(&token.lexeme);
+            self.roles.push(role);
+            self.update_state(i, role);
+        }
+        self.output.push('\n');
+    }
+
+    fn role(&self, i: usize, closed: Option<Scope>) -> Role {
+        let token = &self.tokens[i];
+        let follows_value = i > 0 && self.ends_value(i - 1);
+        match token.token_type {
+            TokenType::RightBrace if closed == Some(Scope::Block) => Role::BlockEnd,
+            TokenType::Less
+                if self.in_signature && self.tokens[i - 1].token_type == TokenType::Identifier =>
+            {
+                Role::TypeParametersOpen
+            }
+            TokenType::Less if !follows_value && self.next_is(i, TokenType::Identifier) => {
+                Role::HtmlOpen
+            }
+            TokenType::Greater if self.scopes.last() == Some(&Scope::OpenTag) => Role::TagEnd,
+            TokenType::Greater if self.in_closing_tag => Role::ClosingTagEnd,
+            TokenType::Greater if self.in_type_parameters => Role::TypeParametersClose,
+            TokenType::SlashGreater if self.scopes.last() == Some(&Scope::OpenTag) => {
+                Role::SelfClosingTagEnd
+            }
+            TokenType::LessSlash => Role::ClosingTag,
+            TokenType::Pipe if self.in_lambda_parameters => Role::LambdaClose,
+            TokenType::Pipe if !follows_value => Role::LambdaOpen,
+            TokenType::Minus | TokenType::Bang if !follows_value => Role::Unary,
+            _ => Role::Plain,
+        }
+    }
+
+    fn separator(&self, i: usize, role: Role, closed: Option<Scope>) -> Separator {
+        let token = &self.tokens[i];
+        let previous = &self.tokens[i - 1];
+        let previous_role = self.roles[i - 1];
+
+        // Comments stay at the end of the line they were on, or on their own line
+        if token.token_type == TokenType::Comment {
+            return match token.line == end_line(previous) {
+                true if matches!(
+                    previous.token_type,
+                    TokenType::LeftParen | TokenType::LeftSquareBracket
+                ) =>
+                {
+                    Separator::None
+                }
+                true => Separator::Space,
+                false => self.newline(i),
+            };
+        }
+        if previous.token_type == TokenType::Comment {
+            // Only a block comment can be followed by code on the line it ends on
+            return match token.line == end_line(previous) {
+                true => Separator::Space,
+                false => self.newline(i),
+            };
+        }
+
+        // Items are separated by a blank line, except for groups of imports
+        if self.scopes.is_empty() && closed.is_none() {
+            match previous.token_type {
+                TokenType::RightBrace => return Separator::BlankLine,
+                TokenType::Semicolon if token.token_type == TokenType::Import => {
+                    return self.newline(i)
+                }
+                TokenType::Semicolon => return Separator::BlankLine,
+                TokenType::RightSquareBracket => return Separator::Newline,
+                _ => {}
+            }
+        }
+
+        match closed {
+            Some(Scope::Block | Scope::StructLiteral | Scope::StructDefinition) => {
+                return match previous.token_type {
+                    TokenType::LeftBrace => Separator::None,
+                    _ => Separator::Newline,
+                }
+            }
+            Some(Scope::Tag { inline: false }) => return Separator::Newline,
+            Some(_) => return Separator::None,
+            None => {}
+        }
+
+        let scope = self.scopes.last().copied();
+        if previous.token_type == TokenType::LeftBrace {
+            return match scope {
+                Some(Scope::Inline) => Separator::None,
+                _ => Separator::Newline,
+            };
+        }
+        if previous_role == Role::TagEnd {
+            return match scope {
+                Some(Scope::Tag { inline: false }) => Separator::Newline,
+                _ => Separator::None,
+            };
+        }
+
+        // The children of an element are separated like the items of a list
+        if let Some(Scope::Tag { inline }) = scope {
+            let ends_child = self.ends_value(i - 1)
+                || matches!(previous_role, Role::ClosingTagEnd | Role::SelfClosingTagEnd);
+            if ends_child && self.starts_value(i, role) {
+                return match inline {
+                    true => Separator::Space,
+                    false => self.newline(i),
+                };
+            }
+        }
+
+        if scope == Some(Scope::OpenTag) {
+            return match (previous_role, &previous.token_type, &token.token_type, role) {
+                (Role::HtmlOpen, _, _, _) => Separator::None,
+                (_, TokenType::Equal, _, _) | (_, _, TokenType::Equal, _) => Separator::None,
+                (_, _, _, Role::TagEnd) => Separator::None,
+                _ => Separator::Space,
+            };
+        }
+        if matches!(previous_role, Role::ClosingTag | Role::HtmlOpen) || role == Role::ClosingTagEnd
+        {
+            return Separator::None;
+        }
+
+        match previous.token_type {
+            TokenType::Semicolon => {
+                return match scope {
+                    Some(Scope::Inline) => Separator::Space,
+                    _ => self.newline(i),
+                }
+            }
+            TokenType::Comma => {
+                return match scope {
+                    Some(Scope::StructLiteral | Scope::StructDefinition) => self.newline(i),
+                    _ => Separator::Space,
+                }
+            }
+            _ => {}
+        }
+
+        if previous_role == Role::BlockEnd {
+            return match token.token_type {
+                TokenType::Else => Separator::Space,
+                TokenType::Semicolon | TokenType::Comma | TokenType::Dot => Separator::None,
+                _ => self.newline(i),
+            };
+        }
+
+        if matches!(
+            previous_role,
+            Role::Unary | Role::LambdaOpen | Role::TypeParametersOpen
+        ) || matches!(
+            role,
+            Role::LambdaClose | Role::TypeParametersOpen | Role::TypeParametersClose
+        ) {
+            return Separator::None;
+        }
+
+        match (&previous.token_type, &token.token_type) {
+            (_, TokenType::Semicolon | TokenType::Comma | TokenType::Colon | TokenType::Dot) => {
+                Separator::None
+            }
+            (TokenType::Dot | TokenType::ColonColon | TokenType::Hash, _)
+            | (_, TokenType::ColonColon) => Separator::None,
+            (TokenType::LeftParen | TokenType::LeftSquareBracket, _) => Separator::None,
+            (
+                TokenType::Identifier
+                | TokenType::RightParen
+                | TokenType::RightSquareBracket
+                | TokenType::Print
+                | TokenType::Assert
+                | TokenType::AssertEq
+                | TokenType::AssertNe
+                | TokenType::Panic
+                | TokenType::RunTest
+                | TokenType::RawJs
+                | TokenType::RawGo,
+                TokenType::LeftParen,
+            ) => Separator::None,
+            (TokenType::Greater, TokenType::LeftParen)
+                if previous_role == Role::TypeParametersClose =>
+            {
+                Separator::None
+            }
+            (
+                TokenType::Identifier | TokenType::RightParen | TokenType::RightSquareBracket,
+                TokenType::LeftSquareBracket,
+            ) => Separator::None,
+            _ => Separator::Space,
+        }
+    }
+
+    fn update_state(&mut self, i: usize, role: Role) {
+        let token = &self.tokens[i];
+        match role {
+            Role::HtmlOpen => self.scopes.push(Scope::OpenTag),
+            Role::TagEnd => {
+                self.scopes.pop();
+                let inline = self.has_inline_children(i);
+                self.scopes.push(Scope::Tag { inline });
+            }
+            Role::SelfClosingTagEnd => {
+                self.scopes.pop();
+            }
+            Role::ClosingTag => self.in_closing_tag = true,
+            Role::ClosingTagEnd => self.in_closing_tag = false,
+            Role::LambdaOpen => self.in_lambda_parameters = true,
+            Role::LambdaClose => self.in_lambda_parameters = false,
+            Role::TypeParametersOpen => self.in_type_parameters = true,
+            Role::TypeParametersClose => self.in_type_parameters = false,
+            _ => {}
+        }
+
+        match token.token_type {
+            TokenType::LeftBrace => {
+                let scope = self.brace_scope(i);
+                self.scopes.push(scope);
+            }
+            TokenType::LeftParen => self.scopes.push(Scope::Paren),
+            TokenType::LeftSquareBracket => self.scopes.push(Scope::Bracket),
+            TokenType::Fn | TokenType::Cmpnt | TokenType::Loader => self.in_signature = true,
+            TokenType::Struct => self.struct_definition = true,
+            TokenType::If => self.if_condition = Some(self.scopes.len()),
+            _ => {}
+        }
+    }
+
+    /// What the `{` at `i` opens, following the same rules as the parser.
+    fn brace_scope(&mut self, i: usize) -> Scope {
+        if std::mem::take(&mut self.struct_definition) {
+            return Scope::StructDefinition;
+        }
+        if std::mem::take(&mut self.in_signature) {
+            return Scope::Block;
+        }
+        if self.if_condition == Some(self.scopes.len()) {
+            self.if_condition = None;
+            return Scope::Block;
+        }
+        match self.scopes.last() {
+            Some(Scope::OpenTag | Scope::Tag { .. }) => Scope::Inline,
+            _ if self.tokens[i - 1].token_type == TokenType::Identifier => Scope::StructLiteral,
+            _ => Scope::Block,
+        }
+    }
+
+    /// Whether the children of the element whose opening tag ends at `i` fit on
+    /// one line, which is when they don't include other elements or comments.
+    fn has_inline_children(&self, i: usize) -> bool {
+        for j in i + 1..self.tokens.len() {
+            match self.tokens[j].token_type {
+                TokenType::LessSlash | TokenType::Eof => return true,
+                TokenType::Comment => return false,
+                TokenType::Less
+                    if self.next_is(j, TokenType::Identifier)
+                        && !is_value_end(&self.tokens[j - 1].token_type) =>
+                {
+                    return false
+                }
+                _ => {}
+            }
+        }
+        true
+    }
+
+    fn ends_value(&self, i: usize) -> bool {
+        match self.tokens[i].token_type {
+            TokenType::RightBrace => self.roles[i] != Role::BlockEnd,
+            ref token_type => is_value_end(token_type),
+        }
+    }
+
+    fn starts_value(&self, i: usize, role: Role) -> bool {
+        match self.tokens[i].token_type {
+            TokenType::Identifier
+            | TokenType::String
+            | TokenType::FString
+            | TokenType::Number
+            | TokenType::True
+            | TokenType::False
+            | TokenType::RawJs
+            | TokenType::RawGo => true,
+            TokenType::LeftBrace => self.tokens[i - 1].token_type != TokenType::Identifier,
+            _ => matches!(role, Role::HtmlOpen | Role::LambdaOpen | Role::Unary),
+        }
+    }
+
+    fn next_is(&self, i: usize, token_type: TokenType) -> bool {
+        self.tokens
+            .get(i + 1)
+            .is_some_and(|token| token.token_type == token_type)
+    }
+
+    /// A newline, or a blank line where the source had one.
+    fn newline(&self, i: usize) -> Separator {
+        match self.tokens[i].line > end_line(&self.tokens[i - 1]) + 1 {
+            true => Separator::BlankLine,
+            false => Separator::Newline,
+        }
+    }
+
+    fn write_separator(&mut self, separator: Separator) {
+        match separator {
+            Separator::None => {}
+            Separator::Space => self.output.push(' '),
+            Separator::Newline | Separator::BlankLine => {
+                if separator == Separator::BlankLine {
+                    self.output.push('\n');
+                }
+                self.output.push('\n');
+                let depth = self
+                    .scopes
+                    .iter()
+                    .filter(|scope| {
+                        matches!(
+                            scope,
+                            Scope::Block
+                                | Scope::StructLiteral
+                                | Scope::StructDefinition
+                                | Scope::Tag { inline: false }
+                        )
+                    })
+                    .count();
+                self.output.push_str(&INDENT.repeat(depth));
+            }
+        }
+    }
+}
+
+fn is_value_end(token_type: &TokenType) -> bool {
+    matches!(
+        token_type,
+        TokenType::Identifier
+            | TokenType::String
+            | TokenType::FString
+            | TokenType::Number
+            | TokenType::True
+            | TokenType::False
+            | TokenType::RightParen
+            | TokenType::RightSquareBracket
+            | TokenType::RightBrace
+    )
+}
+
+/// The line a token ends on, for tokens like block comments and strings that can span
+/// several lines.
+fn end_line(token: &Token) -> usize {
+    token.line + token.lexeme.matches('\n').count()
+}
@@ -1,4 +1,5 @@
 use crate::expression::BlockExpression;
+use crate::token::Token;
 
 #[derive(Debug, Clone)]
 pub struct Parameter {
@@ -10,28 +11,120 @@ pub struct Parameter {
 pub struct StructField {
     pub name: String,
     pub type_annotation: String,
+    pub doc: Option<String>,
 }
 
+/// An attribute of a function, like `#[test]` or `#[should_panic = "overflow"]`.
+#[derive(Debug, Clone)]
+pub struct Attribute {
+    pub name: Token,
+    pub value: Option<String>,
+}
+
+impl Attribute {
+    pub fn find<'a>(attributes: &'a [Attribute], name: &str) -> Option<&'a Attribute> {
+        attributes
+            .iter()
+            .find(|attribute| attribute.name.lexeme == name)
+    }
+}
+
+/// A page served by the runtime, rendered by a component and optionally fed by a loader.
+#[derive(Debug, Clone)]
+pub struct Route {
+    pub path: String,
+    /// The route's module relative to the project root, without an extension.
+    pub module: String,
+    pub component: String,
+    /// The component parameter that receives the loader result.
+    pub parameter: Option<String>,
+    /// The return type of the route's loader, if it has one.
+    pub loader_type: Option<String>,
+}
+
+/// The items that can be documented have the text of the `///` comments before them
+/// in `doc`.
 #[derive(Debug, Clone)]
 pub enum Item {
     Function {
         name: String,
+        type_parameters: Vec<String>,
         parameters: Vec<Parameter>,
         body: BlockExpression,
         return_type: Option<String>,
+        attributes: Vec<Attribute>,
+        doc: Option<String>,
+    },
+    /// A function annotated with `#[server]`. It always runs on the server and is
+    /// called from the client over HTTP.
+    Action {
+        name: String,
+        parameters: Vec<Parameter>,
+        body: BlockExpression,
+        return_type: Option<String>,
+        doc: Option<String>,
     },
     // TODO: Merge into function once we have typing
     Component {
         name: String,
         parameters: Vec<Parameter>,
         body: BlockExpression,
+        doc: Option<String>,
+    },
+    /// Server-only data fetching for the component with the same name. The result is
+    /// passed to the component when rendering its route.
+    Loader {
+        name: String,
+        body: BlockExpression,
+        return_type: String,
+        doc: Option<String>,
     },
     Import {
         path: Vec<String>,
     },
     TestRunner,
+    Router {
+        routes: Vec<Route>,
+    },
     Struct {
         name: String,
         fields: Vec<StructField>,
+        doc: Option<String>,
     },
 }
+
+impl Item {
+    pub fn doc(&self) -> Option<&str> {
+        match self {
+            Item::Function { doc, .. }
+            | Item::Action { doc, .. }
+            | Item::Component { doc, .. }
+            | Item::Loader { doc, .. }
+            | Item::Struct { doc, .. } => doc.as_deref(),
+            Item::Import { .. } | Item::TestRunner | Item::Router { .. } => None,
+        }
+    }
+
+    fn doc_mut(&mut self) -> Option<&mut Option<String>> {
+        match self {
+            Item::Function { doc, .. }
+            | Item::Action { doc, .. }
+            | Item::Component { doc, .. }
+            | Item::Loader { doc, .. }
+            | Item::Struct { doc, .. } => Some(doc),
+            Item::Import { .. } | Item::TestRunner | Item::Router { .. } => None,
+        }
+    }
+
+    /// Puts `doc` before the item's own doc comment, which is the one written between
+    /// its attributes and its declaration.
+    pub fn with_doc(mut self, doc: Option<String>) -> Item {
+        if let Some(item_doc) = self.doc_mut() {
+            *item_doc = match (doc, item_doc.take()) {
+                (Some(doc), Some(item_doc)) => Some(format!("{}\n{}", doc, item_doc)),
+                (doc, item_doc) => doc.or(item_doc),
+            };
+        }
+        self
+    }
+}
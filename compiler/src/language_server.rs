@@ -0,0 +1,396 @@
+use crate::analysis::{CompletionKind, Definition, ModuleAnalysis, Severity, SymbolKind};
+use anyhow::Result;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{
+    Completion, DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as RequestTrait,
+};
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionOptions, CompletionParams, CompletionResponse,
+    Diagnostic, DiagnosticSeverity, DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse,
+    GotoDefinitionParams, GotoDefinitionResponse, Hover, HoverContents, HoverParams,
+    HoverProviderCapability, Location, MarkupContent, MarkupKind, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, TextDocumentPositionParams,
+    TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Runs a language server over stdin and stdout until the client shuts it down.
+pub fn run() -> Result<()> {
+    let (connection, io_threads) = Connection::stdio();
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncKind::FULL.into()),
+        definition_provider: Some(OneOf::Left(true)),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec![":".to_string()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut server = LanguageServer {
+        connection: &connection,
+        documents: HashMap::new(),
+    };
+    server.main_loop()?;
+
+    // The IO threads only finish once the connection is closed
+    drop(connection);
+    io_threads.join()?;
+    Ok(())
+}
+
+struct LanguageServer<'a> {
+    connection: &'a Connection,
+    /// The contents of the documents open in the editor, which may not be saved yet.
+    documents: HashMap<PathBuf, String>,
+}
+
+impl LanguageServer<'_> {
+    fn main_loop(&mut self) -> Result<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+                    self.handle_request(request)?;
+                }
+                // There is no one to reply to about a notification that can't be handled,
+                // so it's logged for the client's output
+                Message::Notification(notification) => {
+                    if let Err(error) = self.handle_notification(notification) {
+                        eprintln!("{}", error);
+                    }
+                }
+                Message::Response(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn handle_request(&self, request: Request) -> Result<()> {
+        match request.method.as_str() {
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Self::definition),
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Self::hover),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, Self::document_symbols)
+            }
+            Completion::METHOD => self.respond::<Completion>(request, Self::completion),
+            _ => Ok(self.connection.sender.send(
+                Response::new_err(
+                    request.id,
+                    ErrorCode::MethodNotFound as i32,
+                    format!("Unsupported request {}", request.method),
+                )
+                .into(),
+            )?),
+        }
+    }
+
+    fn respond<R: RequestTrait>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Result<()> {
+        let response = match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(error) => Response::new_err(
+                request.id,
+                ErrorCode::InvalidParams as i32,
+                format!("Invalid params for {}: {}", request.method, error),
+            ),
+        };
+        Ok(self.connection.sender.send(response.into())?)
+    }
+
+    fn handle_notification(&mut self, notification: Notification) -> Result<()> {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                let params = notification
+                    .extract::<lsp_types::DidOpenTextDocumentParams>(DidOpenTextDocument::METHOD)?;
+                self.update(params.text_document.uri, Some(params.text_document.text))
+            }
+            DidChangeTextDocument::METHOD => {
+                let mut params = notification.extract::<lsp_types::DidChangeTextDocumentParams>(
+                    DidChangeTextDocument::METHOD,
+                )?;
+                // Documents are always synced in full
+                let text = params.content_changes.pop().map(|change| change.text);
+                self.update(params.text_document.uri, text)
+            }
+            DidCloseTextDocument::METHOD => {
+                let params = notification.extract::<lsp_types::DidCloseTextDocumentParams>(
+                    DidCloseTextDocument::METHOD,
+                )?;
+                self.update(params.text_document.uri, None)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// Stores the new contents of a document, or forgets it when it was closed, and
+    /// publishes its diagnostics.
+    fn update(&mut self, uri: Url, text: Option<String>) -> Result<()> {
+        let Ok(path) = uri.to_file_path() else {
+            return Ok(());
+        };
+        let path = path.canonicalize().unwrap_or(path);
+        let diagnostics = match text {
+            Some(text) => {
+                self.documents.insert(path.clone(), text);
+                let module = self.load(&path).unwrap();
+                module
+                    .diagnostics
+                    .iter()
+                    .chain(&module.check(&self.project(&path), |path| self.load(path)))
+                    .map(|diagnostic| Diagnostic {
+                        range: range(
+                            &module,
+                            diagnostic.line,
+                            diagnostic.column,
+                            diagnostic.column + diagnostic.length,
+                        ),
+                        severity: Some(match diagnostic.severity {
+                            Severity::Error => DiagnosticSeverity::ERROR,
+                            Severity::Warning => DiagnosticSeverity::WARNING,
+                        }),
+                        source: Some("fsf".to_string()),
+                        message: diagnostic.message.clone(),
+                        ..Default::default()
+                    })
+                    .collect()
+            }
+            None => {
+                self.documents.remove(&path);
+                Vec::new()
+            }
+        };
+
+        let params = PublishDiagnosticsParams::new(uri, diagnostics, None);
+        let notification = Notification::new(PublishDiagnostics::METHOD.to_string(), params);
+        Ok(self.connection.sender.send(notification.into())?)
+    }
+
+    /// Analyses a module, preferring its contents in the editor over the file on disk.
+    fn load(&self, path: &Path) -> Option<ModuleAnalysis> {
+        let path = path.canonicalize().unwrap_or(path.to_path_buf());
+        let source = match self.documents.get(&path) {
+            Some(source) => source.clone(),
+            None => std::fs::read_to_string(&path).ok()?,
+        };
+        Some(ModuleAnalysis::new(path, &source))
+    }
+
+    /// The other modules of the project a module belongs to.
+    fn project(&self, path: &Path) -> Vec<ModuleAnalysis> {
+        let Some(Ok(entries)) = path.parent().map(std::fs::read_dir) else {
+            return Vec::new();
+        };
+        entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|other| other.extension().is_some_and(|ext| ext == "fsf") && other != path)
+            .filter_map(|other| self.load(&other))
+            .collect()
+    }
+
+    /// The module and identifier token at a position.
+    fn identifier_at(
+        &self,
+        position: &TextDocumentPositionParams,
+    ) -> Option<(ModuleAnalysis, usize)> {
+        let module = self.load(&position.text_document.uri.to_file_path().ok()?)?;
+        let line = position.position.line as usize + 1;
+        let index = module.identifier_at(line, column(&module, line, position.position))?;
+        Some((module, index))
+    }
+
+    /// The location a definition points to, along with how it was declared.
+    fn resolve(&self, module: &ModuleAnalysis, index: usize) -> Option<(Location, String)> {
+        match module.definition(index)? {
+            Definition::Symbol(symbol) => {
+                Some((token_location(module, symbol.token)?, symbol.detail.clone()))
+            }
+            Definition::Module(path) => Some((
+                Location::new(
+                    Url::from_file_path(path.canonicalize().ok()?).ok()?,
+                    Range::default(),
+                ),
+                format!("mod {}", module.tokens[index].lexeme),
+            )),
+            Definition::Imported { module, name } => {
+                let module = self.load(&module)?;
+                let symbol = module.item(&name)?;
+                Some((
+                    token_location(&module, symbol.token)?,
+                    symbol.detail.clone(),
+                ))
+            }
+        }
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let (module, index) = self.identifier_at(&params.text_document_position_params)?;
+        let (location, _) = self.resolve(&module, index)?;
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let (module, index) = self.identifier_at(&params.text_document_position_params)?;
+        let (_, detail) = self.resolve(&module, index)?;
+        let token = &module.tokens[index];
+        Some(Hover {
+            contents: HoverContents::Markup(MarkupContent {
+                kind: MarkupKind::Markdown,
+                value: format!("```fsf\n{}\n```", detail),
+            }),
+            range: Some(range(
+                &module,
+                token.line,
+                token.column,
+                token.column + token.lexeme.len(),
+            )),
+        })
+    }
+
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let module = self.load(&params.text_document.uri.to_file_path().ok()?)?;
+        let symbols = module
+            .symbols
+            .iter()
+            .filter(|symbol| symbol.is_item())
+            .map(|symbol| {
+                let name = &module.tokens[symbol.token];
+                let start = &module.tokens[symbol.scope.start];
+                let end = &module.tokens[symbol.scope.end - 1];
+                let (detail, _) = symbol.detail.split_once('(').unwrap_or_default();
+                #[allow(deprecated)]
+                DocumentSymbol {
+                    name: symbol.name.clone(),
+                    detail: Some(detail.to_string()).filter(|d| !d.is_empty()),
+                    kind: symbol_kind(symbol.kind),
+                    tags: None,
+                    deprecated: None,
+                    range: Range::new(
+                        position(&module, start.line, start.column),
+                        position(&module, end.line, end.column + end.lexeme.len()),
+                    ),
+                    selection_range: range(
+                        &module,
+                        name.line,
+                        name.column,
+                        name.column + name.lexeme.len(),
+                    ),
+                    children: None,
+                }
+            })
+            .collect();
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+
+    fn completion(&self, params: CompletionParams) -> Option<CompletionResponse> {
+        let position = params.text_document_position.position;
+        let module = self.load(
+            &params
+                .text_document_position
+                .text_document
+                .uri
+                .to_file_path()
+                .ok()?,
+        )?;
+        let line = position.line as usize + 1;
+        let completions = module
+            .completions(line, column(&module, line, position), |path| {
+                self.load(path)
+            })
+            .into_iter()
+            .map(|completion| CompletionItem {
+                label: completion.label,
+                kind: Some(match completion.kind {
+                    CompletionKind::Symbol(SymbolKind::Function | SymbolKind::Loader) => {
+                        CompletionItemKind::FUNCTION
+                    }
+                    CompletionKind::Symbol(SymbolKind::Component) => CompletionItemKind::CLASS,
+                    CompletionKind::Symbol(SymbolKind::Struct) => CompletionItemKind::STRUCT,
+                    CompletionKind::Symbol(SymbolKind::Variable | SymbolKind::Parameter) => {
+                        CompletionItemKind::VARIABLE
+                    }
+                    CompletionKind::Module => CompletionItemKind::MODULE,
+                    CompletionKind::Keyword => CompletionItemKind::KEYWORD,
+                }),
+                detail: completion.detail,
+                ..Default::default()
+            })
+            .collect();
+        Some(CompletionResponse::Array(completions))
+    }
+}
+
+fn token_location(module: &ModuleAnalysis, index: usize) -> Option<Location> {
+    let token = &module.tokens[index];
+    Some(Location::new(
+        Url::from_file_path(&module.path).ok()?,
+        range(
+            module,
+            token.line,
+            token.column,
+            token.column + token.lexeme.len(),
+        ),
+    ))
+}
+
+fn symbol_kind(kind: SymbolKind) -> lsp_types::SymbolKind {
+    match kind {
+        SymbolKind::Function | SymbolKind::Loader => lsp_types::SymbolKind::FUNCTION,
+        SymbolKind::Component => lsp_types::SymbolKind::CLASS,
+        SymbolKind::Struct => lsp_types::SymbolKind::STRUCT,
+        SymbolKind::Variable | SymbolKind::Parameter => lsp_types::SymbolKind::VARIABLE,
+    }
+}
+
+/// Converts a 1-based line and 0-based column to an LSP position. Columns are byte
+/// offsets, while LSP positions count the UTF-16 code units of the line.
+fn position(module: &ModuleAnalysis, line: usize, column: usize) -> Position {
+    let text = module
+        .source
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or("");
+    let prefix = text
+        .char_indices()
+        .take_while(|(i, _)| *i < column)
+        .map(|(_, c)| c.len_utf16())
+        .sum::<usize>();
+    // A token that spans lines ends past the end of its first line
+    let past_end = column.saturating_sub(text.len());
+    Position::new(line.saturating_sub(1) as u32, (prefix + past_end) as u32)
+}
+
+/// Converts the character of an LSP position on a 1-based line to a byte column.
+fn column(module: &ModuleAnalysis, line: usize, position: Position) -> usize {
+    let text = module
+        .source
+        .lines()
+        .nth(line.saturating_sub(1))
+        .unwrap_or("");
+    let mut units = 0;
+    for (i, c) in text.char_indices() {
+        if units >= position.character as usize {
+            return i;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// A range within a single line.
+fn range(module: &ModuleAnalysis, line: usize, start: usize, end: usize) -> Range {
+    Range::new(position(module, line, start), position(module, line, end))
+}
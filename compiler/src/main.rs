@@ -1,36 +1,119 @@
+use crate::analysis::{ModuleAnalysis, Severity};
+use crate::build_context::BuildContext;
 use crate::parser::Parser;
 use crate::scanner::Scanner;
 use crate::targets::go_target::GoTarget;
 use crate::targets::js_target::JsTarget;
+use crate::targets::source_map::{map_positions, SourceMaps};
 use crate::targets::{Module, Program};
+use crate::test_results::{TestResult, TestRun};
 use anyhow::anyhow;
 use anyhow::Result;
 use clap::Parser as _;
+use itertools::Itertools;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufRead, BufReader, Read};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::process::{Child, Command, Stdio};
+use walkdir::WalkDir;
 
+mod analysis;
+mod build_context;
+mod dev_server;
+mod documentation;
 mod expression;
+mod formatter;
 mod item;
+mod language_server;
 mod parser;
 mod scanner;
 mod statement;
 mod targets;
+mod test_results;
 mod token;
 mod transformers;
 
+/// The Go module a web application is built in, with the server that serves its routes.
+const RUNTIME: [(&str, &str); 2] = [
+    ("go.mod", include_str!("../../runtime/go.mod")),
+    ("main.go", include_str!("../../runtime/main.go")),
+];
+
+/// The npm package the JavaScript of a web application is bundled in.
+const JAVASCRIPT: [(&str, &str); 2] = [
+    (
+        "package.json",
+        include_str!("../../javascript/package.json"),
+    ),
+    ("build.js", include_str!("../../javascript/build.js")),
+];
+
 #[derive(clap::ValueEnum, Clone, Debug)]
 enum Target {
     Go,
     Js,
 }
 
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum Stage {
+    /// The tokens of each module
+    Tokens,
+    /// The parsed program
+    Ast,
+    /// The program after the transformers ran
+    Transformed,
+    /// The generated Go source
+    Go,
+    /// The generated JavaScript source
+    Js,
+}
+
+/// The commands whose transformers `fsf dump` runs.
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum Pipeline {
+    /// `fsf serve` and `fsf build`
+    Web,
+    /// `fsf run`
+    Run,
+    /// `fsf test`
+    Test,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum TestTarget {
+    Go,
+    Js,
+    /// Both Go and JavaScript, reporting the tests whose results differ between them
+    All,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum TestFormat {
+    /// Print each test as it runs
+    Human,
+    /// A JUnit XML report
+    Junit,
+    /// A JSON report
+    Json,
+}
+
+#[derive(clap::ValueEnum, Clone, Debug)]
+enum MessageFormat {
+    Human,
+    /// One JSON object per diagnostic, on its own line
+    Json,
+}
+
 #[derive(clap::Parser)]
 #[command(name = "fsf")]
 struct Cli {
     #[command(subcommand)]
     command: Commands,
+
+    /// The directory to write the build to, defaults to `.fsf/{command}` inside the
+    /// project, or `build` inside the project for `fsf build`
+    #[arg(long, global = true)]
+    out_dir: Option<PathBuf>,
 }
 
 #[derive(clap::Subcommand)]
@@ -39,6 +122,20 @@ enum Commands {
     Serve {
         /// The path to serve from
         path: PathBuf,
+
+        /// Recompile on changes and reload connected browsers
+        #[arg(long)]
+        watch: bool,
+    },
+    /// Build a deployable binary from the specified path
+    Build {
+        /// The path to build from
+        path: PathBuf,
+
+        /// Minify the JavaScript bundle, leave the sources out of its source maps and
+        /// strip debug information from the binary
+        #[arg(long)]
+        release: bool,
     },
     /// Run from the specified path
     Run {
@@ -52,6 +149,64 @@ enum Commands {
     Test {
         path: PathBuf,
 
+        /// Only run the tests whose name, like `module::test_name`, contains this
+        filter: Option<String>,
+
+        #[arg(long, value_enum, default_value_t = TestTarget::Go)]
+        target: TestTarget,
+
+        /// How to report the results, the reports are written to stdout
+        #[arg(long, value_enum, default_value_t = TestFormat::Human)]
+        format: TestFormat,
+
+        /// Run the tests marked `#[ignore]` as well
+        #[arg(long)]
+        include_ignored: bool,
+
+        /// Run the tests in parallel on Go. The test binary runs itself again for every
+        /// test, with FSF_TEST_ONLY naming the test to run, and at most as many at once as
+        /// there are CPUs. Each test's output is kept together and the results are
+        /// reported in the order the tests are declared
+        #[arg(long)]
+        parallel: bool,
+    },
+    /// Format the fsf files in the specified path
+    Fmt {
+        /// A file, or a directory to format all the fsf files in
+        path: PathBuf,
+
+        /// Report the files that aren't formatted instead of formatting them
+        #[arg(long)]
+        check: bool,
+    },
+    /// Report the problems in the specified path without compiling it
+    Check {
+        /// A file, or a project directory to check all the fsf files in
+        path: PathBuf,
+
+        #[arg(long, value_enum, default_value_t = MessageFormat::Human)]
+        message_format: MessageFormat,
+    },
+    /// Generate HTML documentation for the specified path and the standard library
+    Doc {
+        /// The project directory to document
+        path: PathBuf,
+    },
+    /// Start a language server over stdio for editor support
+    Lsp,
+    /// Print the output of a compiler stage for the specified path
+    Dump {
+        /// A file, or a project directory
+        path: PathBuf,
+
+        #[arg(long, value_enum)]
+        stage: Stage,
+
+        /// The command to transform and compile the program like
+        #[arg(long, value_enum, default_value_t = Pipeline::Web)]
+        pipeline: Pipeline,
+
+        /// The target whose transformers run for the transformed stage
         #[arg(long, value_enum, default_value_t = Target::Go)]
         target: Target,
     },
@@ -59,138 +214,367 @@ enum Commands {
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
+    let out_dir = cli.out_dir.clone();
 
-    let _ = std::fs::remove_dir_all(".dist");
     match &cli.command {
-        Commands::Serve { path } => serve(path),
-        Commands::Run { path, target } => run(path, target),
-        Commands::Test { path, target } => test(path, target),
+        Commands::Serve { path, watch: false } => {
+            serve(path, &BuildContext::new(path, "serve", out_dir))
+        }
+        Commands::Serve { path, watch: true } => {
+            let context = BuildContext::new(path, "serve", out_dir);
+            context.clean()?;
+            dev_server::DevServer::new(path.into(), context).watch()
+        }
+        Commands::Build { path, release } => {
+            let out_dir = out_dir.unwrap_or(path.join("build"));
+            build(
+                path,
+                &BuildContext::new(path, "build", Some(out_dir)),
+                *release,
+            )
+        }
+        Commands::Run { path, target } => run(
+            path,
+            target,
+            &BuildContext::new(path, &format!("run-{:?}", target).to_lowercase(), out_dir),
+        ),
+        Commands::Test {
+            path,
+            filter,
+            target,
+            format,
+            include_ignored,
+            parallel,
+        } => {
+            let targets = match target {
+                TestTarget::Go => vec![Target::Go],
+                TestTarget::Js => vec![Target::Js],
+                TestTarget::All => vec![Target::Go, Target::Js],
+            };
+            let target_count = targets.len();
+            let runs = targets
+                .into_iter()
+                .map(|target| {
+                    let name = format!("test-{:?}", target).to_lowercase();
+                    // Each target needs a directory of its own when they're all run
+                    let out_dir = match target_count {
+                        1 => out_dir.clone(),
+                        _ => out_dir.as_ref().map(|out_dir| out_dir.join(&name)),
+                    };
+                    (target, BuildContext::new(path, &name, out_dir))
+                })
+                .collect::<Vec<_>>();
+            // The test runner reads its options from the environment
+            let environment = [
+                ("FSF_TEST_INCLUDE_IGNORED", *include_ignored),
+                ("FSF_TEST_PARALLEL", *parallel),
+            ]
+            .into_iter()
+            .filter_map(|(name, is_set)| is_set.then_some((name, "1")))
+            .collect::<Vec<_>>();
+            test(path, filter.as_deref(), format, &environment, &runs)
+        }
+        Commands::Fmt { path, check } => fmt(path, *check),
+        Commands::Check {
+            path,
+            message_format,
+        } => check(path, message_format),
+        Commands::Doc { path } => doc(path, &BuildContext::new(path, "doc", out_dir)),
+        Commands::Lsp => language_server::run(),
+        Commands::Dump {
+            path,
+            stage,
+            pipeline,
+            target,
+        } => {
+            let project = match path.is_dir() {
+                true => path.as_path(),
+                false => path.parent().unwrap_or(Path::new(".")),
+            };
+            dump(
+                path,
+                project,
+                stage,
+                pipeline,
+                target,
+                &BuildContext::new(project, "dump", out_dir),
+            )
+        }
     }
 }
 
-fn serve(path: &Path) -> Result<()> {
-    // TODO: Handle multiple routes
-    let mut program = std::fs::read_dir(path)?
-        .filter_map(|entry| match entry {
-            Ok(entry) if entry.path().is_file() => Some(entry.path()),
-            _ => None,
-        })
-        .map(parse_module_from_file)
-        .collect::<Result<Program>>()?;
+fn serve(path: &Path, context: &BuildContext) -> Result<()> {
+    context.clean()?;
+    let source_maps = compile_web(path, context, false)?;
 
-    let mut std_lib_transformer = transformers::StandardLibraryTransformer::new(path.into());
-    std_lib_transformer.transform(&mut program)?;
+    // The server's output is passed through so its logs are visible
+    context.init_go_module()?;
+    match run_toolchain(context.command("go").arg("run").arg("."), &source_maps)? {
+        true => Ok(()),
+        false => Err(anyhow!("Failed to run Go command")),
+    }
+}
+
+fn build(path: &Path, context: &BuildContext, release: bool) -> Result<()> {
+    context.clean()?;
+    let source_maps = compile_web(path, context, release)?;
+
+    let binary_name = path
+        .canonicalize()?
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or("app".to_string());
+
+    context.init_go_module()?;
+    let mut go_build = context.command("go");
+    go_build.arg("build").arg("-o").arg(&binary_name);
+    if release {
+        go_build.arg("-trimpath").arg("-ldflags=-s -w");
+    }
+    match run_toolchain(&mut go_build, &source_maps)? {
+        true => {
+            println!("Built {}", context.out_dir().join(binary_name).display());
+            Ok(())
+        }
+        false => Err(anyhow!("Failed to build Go binary")),
+    }
+}
 
+/// Compiles a web application into a Go package in the build's output directory, with
+/// the bundled JavaScript and static assets from the project's `public` directory embedded.
+fn compile_web(path: &Path, context: &BuildContext, release: bool) -> Result<SourceMaps> {
+    let source_maps = generate_web(path, parse_program(path)?, context)?;
+
+    let mut npm_build = context.js_command("npm");
+    npm_build.arg("run").arg("build");
+    if release {
+        npm_build.arg("--").arg("--release");
+    }
+    if !context.js_command("npm").arg("install").status()?.success()
+        || !run_toolchain(&mut npm_build, &source_maps)?
+    {
+        return Err(anyhow!("Failed to bundle JavaScript"));
+    }
+
+    copy_public_assets(path, context)?;
+    Ok(source_maps)
+}
+
+/// Generates the Go package and the unbundled JavaScript modules of a web application.
+fn generate_web(path: &Path, mut program: Program, context: &BuildContext) -> Result<SourceMaps> {
+    transform_web(path, &mut program)?;
     let js_program = program.clone();
 
-    let compile_dir = PathBuf::from(".dist/runtime");
-    std::fs::create_dir_all(&compile_dir)?;
-    let mut compiler = GoTarget::new();
-    compiler.compile(program, &compile_dir)?;
+    let mut compiler = GoTarget::new(path.into());
+    let mut source_maps = compiler.compile(program, context.out_dir())?;
 
-    setup_runtime()?;
+    write_files(context.out_dir(), &RUNTIME)?;
 
     // Compile Javascript
-    let js_dir = compile_dir.join("javascript");
-    std::fs::create_dir_all(&js_dir)?;
-    Command::new("rsync")
-        .arg("-a")
-        .arg("../javascript/")
-        .arg(&js_dir)
-        .status()?;
-
-    // // TODO: Filter out modules to only include app/ directory
-    // for module in js_program {
-    //     let mut js_compiler = JsCompiler::new();
-    //     js_compiler.compile(path, vec![module], &js_dir, false)?;
-    // }
-    let mut js_compiler = JsTarget::new();
-    js_compiler.compile(js_program, &js_dir, false)?;
-
-    let cwd = std::env::current_dir()?;
-    std::env::set_current_dir(&js_dir)?;
-    Command::new("npm").arg("install").status()?;
-    Command::new("npm").arg("run").arg("build").status()?;
-    std::env::set_current_dir(cwd)?;
-
-    std::env::set_current_dir(compile_dir)?;
-    let _ = Command::new("go")
-        .arg("mod")
-        .arg("init")
-        .arg("fsf")
-        .output()?;
-    let output = Command::new("go").arg("run").arg(".").output()?;
-
-    match output.status.success() {
-        true => Ok(()),
-        false => {
-            eprintln!(
-                "Failed to run Go command: {}",
-                String::from_utf8_lossy(&output.stderr)
-            );
-            Err(anyhow!("Failed to run Go command"))
+    let js_dir = context.js_dir();
+    write_files(&js_dir, &JAVASCRIPT)?;
+
+    let mut js_compiler = JsTarget::new(path.into());
+    source_maps.extend(js_compiler.compile(js_program, &js_dir, false)?);
+    Ok(source_maps)
+}
+
+/// Runs the transformers of a web application build.
+fn transform_web(path: &Path, program: &mut Program) -> Result<()> {
+    let mut std_lib_transformer = transformers::StandardLibraryTransformer::new(path.into());
+    std_lib_transformer.transform(program)?;
+
+    transformers::NumberTransformer::new().transform(program)?;
+
+    let mut router_transformer = transformers::RouterTransformer::new(path.into());
+    router_transformer.transform(program);
+    Ok(())
+}
+
+/// Runs the transformers of a program run as it is.
+fn transform_run(program: &mut Program) -> Result<()> {
+    transformers::NumberTransformer::new().transform(program)
+}
+
+/// Runs the transformers that turn a program's tests into an executable for `target`.
+fn transform_tests(
+    path: &Path,
+    program: &mut Program,
+    filter: Option<&str>,
+    target: &Target,
+) -> Result<()> {
+    let mut test_runner_transformer =
+        transformers::TestRunnerTransformer::new(path.into(), filter.map(str::to_string));
+    test_runner_transformer.transform(program);
+
+    let mut std_lib_transformer = transformers::StandardLibraryTransformer::new(path.into());
+    std_lib_transformer.transform(program)?;
+
+    // Runs before identifiers are renamed, since it finds functions by their name
+    transformers::NumberTransformer::new().transform(program)?;
+
+    match target {
+        Target::Go => {
+            let mut identifier_transformer =
+                transformers::GoIdentifierTransformer::new(path.into());
+            identifier_transformer.transform(program);
+        }
+        Target::Js => {
+            let mut identifier_transformer =
+                transformers::JsIdentifierTransformer::new(path.into());
+            identifier_transformer.transform(program);
         }
     }
+    Ok(())
+}
+
+/// Runs a toolchain command, rewriting the positions in its error output that point
+/// into generated code so that they point at the fsf source instead.
+fn run_toolchain(command: &mut Command, source_maps: &SourceMaps) -> Result<bool> {
+    let mut child = command.stderr(Stdio::piped()).spawn()?;
+    map_error_output(&mut child, command, source_maps)?;
+    Ok(child.wait()?.success())
+}
+
+/// Like `run_toolchain`, but collects what the command writes to stdout instead of
+/// printing it.
+fn run_toolchain_with_output(
+    command: &mut Command,
+    source_maps: &SourceMaps,
+) -> Result<(bool, String)> {
+    let mut child = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+    let mut stdout = child.stdout.take().unwrap();
+    // Read stdout alongside stderr so that neither fills up and blocks the command
+    let reader = std::thread::spawn(move || {
+        let mut output = String::new();
+        stdout.read_to_string(&mut output).map(|_| output)
+    });
+    map_error_output(&mut child, command, source_maps)?;
+    let output = reader.join().unwrap()?;
+    Ok((child.wait()?.success(), output))
+}
+
+fn map_error_output(child: &mut Child, command: &Command, source_maps: &SourceMaps) -> Result<()> {
+    let cwd = command
+        .get_current_dir()
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    for line in BufReader::new(child.stderr.take().unwrap()).lines() {
+        eprintln!("{}", map_positions(&line?, source_maps, &cwd));
+    }
+    Ok(())
+}
+
+/// Copies the project's `public` directory next to the bundle, where it is served from.
+fn copy_public_assets(path: &Path, context: &BuildContext) -> Result<()> {
+    let public_dir = path.join("public");
+    if public_dir.is_dir() {
+        copy_dir(&public_dir, &context.js_dir().join("dist"))?;
+    }
+    Ok(())
+}
+
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    for entry in WalkDir::new(from) {
+        let entry = entry?;
+        let destination = to.join(entry.path().strip_prefix(from)?);
+        if entry.file_type().is_dir() {
+            std::fs::create_dir_all(destination)?;
+        } else {
+            std::fs::copy(entry.path(), destination)?;
+        }
+    }
+    Ok(())
 }
 
 fn parse_module_from_file(path: PathBuf) -> Result<Module> {
     let mut file = File::open(&path)?;
     let mut contents = String::new();
     file.read_to_string(&mut contents)?;
-    parse_module(contents, path)
+    let source = path.canonicalize()?;
+    let mut module = parse_module(contents, path)?;
+    module.source = Some(source);
+    Ok(module)
 }
 
 fn parse_module(contents: String, path: PathBuf) -> Result<Module> {
     let mut scanner = Scanner::new(contents);
     let tokens = scanner.scan_tokens();
-    let mut parser = Parser::new(tokens, path.clone());
+    if let Some(error) = scanner.errors().first() {
+        return Err(anyhow!("{}:{}: {}", path.display(), error.line, error));
+    }
+    let mut parser = Parser::new(tokens);
+    let items = parser.parse().map_err(|errors| {
+        anyhow!(errors
+            .iter()
+            .map(|error| format!("{}:{}: {}", path.display(), error.token().line, error))
+            .join("\n"))
+    })?;
     Ok(Module {
+        source: Some(path.clone()),
         path,
-        items: parser.parse(),
+        items,
     })
 }
 
-fn run(path: &Path, target: &Target) -> Result<()> {
-    let program = std::fs::read_dir(path)?
-        .filter_map(|entry| match entry {
-            Ok(entry) if entry.path().is_file() => Some(entry.path()),
-            _ => None,
-        })
+/// Parses the modules of a project, including the ones in its subdirectories, like the
+/// route `chirps/[id].fsf`.
+fn parse_program(path: &Path) -> Result<Program> {
+    fsf_files(path)
+        .into_iter()
         .map(parse_module_from_file)
-        .collect::<Result<Program>>()?;
+        .collect()
+}
+
+/// The fsf files in a directory and its subdirectories, in a stable order.
+fn fsf_files(path: &Path) -> Vec<PathBuf> {
+    WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        // Skip build output and installed packages
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !entry.file_name().to_string_lossy().starts_with('.')
+                    && entry.file_name() != "node_modules"
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "fsf"))
+        .map(|entry| entry.into_path())
+        .collect()
+}
+
+fn run(path: &Path, target: &Target, context: &BuildContext) -> Result<()> {
+    let mut program = parse_program(path)?;
+    transform_run(&mut program)?;
+    context.clean()?;
 
     match target {
         Target::Go => {
-            let mut compiler = GoTarget::new();
-            std::fs::create_dir_all(".dist/runtime")?;
-            let compile_dir = PathBuf::from(".dist/runtime");
-            compiler.compile(program, &compile_dir)?;
+            let mut compiler = GoTarget::new(path.into());
+            let source_maps = compiler.compile(program, context.out_dir())?;
 
             // TODO: Make this part of the compiler
-            std::env::set_current_dir(compile_dir)?;
-            let _ = Command::new("go")
-                .arg("mod")
-                .arg("init")
-                .arg("fsf")
-                .output()?;
-            let output = Command::new("go").arg("run").arg(".").output()?;
-
-            match output.status.success() {
-                true => {
-                    print!("{}", String::from_utf8_lossy(&output.stdout));
-                    Ok(())
-                }
+            context.init_go_module()?;
+            match run_toolchain(context.command("go").arg("run").arg("."), &source_maps)? {
+                true => Ok(()),
                 false => Err(anyhow!("Failed to run command")),
             }
         }
         Target::Js => {
-            let mut compiler = JsTarget::new();
-            let compile_dir = PathBuf::from("./dist/js");
-            std::fs::create_dir_all(&compile_dir)?;
-            compiler.compile(program, &compile_dir, true)?;
-
-            std::env::set_current_dir(compile_dir)?;
-            match Command::new("node").arg("main.js").status()?.success() {
+            let mut compiler = JsTarget::new(path.into());
+            let source_maps = compiler.compile(program, context.out_dir(), true)?;
+
+            match run_toolchain(
+                context
+                    .command("node")
+                    .arg("--enable-source-maps")
+                    .arg("main.js"),
+                &source_maps,
+            )? {
                 true => Ok(()),
                 false => Err(anyhow!("Failed to run command")),
             }
@@ -198,79 +582,276 @@ fn run(path: &Path, target: &Target) -> Result<()> {
     }
 }
 
-fn test(path: &Path, target: &Target) -> Result<()> {
-    let mut program = std::fs::read_dir(path)?
-        .filter_map(|entry| match entry {
-            Ok(entry) if entry.path().is_file() => Some(entry.path()),
-            _ => None,
-        })
-        .map(parse_module_from_file)
-        .collect::<Result<Program>>()?;
+/// Runs the tests on each target. When there are several targets, the tests whose
+/// results differ between them are reported as well.
+fn test(
+    path: &Path,
+    filter: Option<&str>,
+    format: &TestFormat,
+    environment: &[(&str, &str)],
+    targets: &[(Target, BuildContext)],
+) -> Result<()> {
+    if let ([(target, context)], TestFormat::Human) = (targets, format) {
+        let (mut command, source_maps) = build_tests(path, filter, target, context)?;
+        command.envs(environment.iter().copied());
+        return match run_toolchain(&mut command, &source_maps)? {
+            true => Ok(()),
+            false => Err(anyhow!("Tests failed")),
+        };
+    }
 
-    let mut test_runner_transformer = transformers::TestRunnerTransformer::new(path.into());
-    test_runner_transformer.transform(&mut program);
+    let mut runs = Vec::new();
+    let mut passed = true;
+    for (target, context) in targets {
+        let (mut command, source_maps) = build_tests(path, filter, target, context)?;
+        command
+            .envs(environment.iter().copied())
+            .env("FSF_TEST_FORMAT", "json");
+        let (target_passed, output) = run_toolchain_with_output(&mut command, &source_maps)?;
+        passed &= target_passed;
+
+        let mut results = Vec::new();
+        for line in output.lines() {
+            match TestResult::parse(line)? {
+                Some(result) => results.push(result),
+                // Keep what the tests print out of the report
+                None => eprintln!("{}", line),
+            }
+        }
+        runs.push(TestRun {
+            target: format!("{:?}", target).to_lowercase(),
+            results,
+        });
+    }
 
-    let mut std_lib_transformer = transformers::StandardLibraryTransformer::new(path.into());
-    std_lib_transformer.transform(&mut program)?;
+    let differences = test_results::differences(&runs);
+    match format {
+        TestFormat::Human => print!("{}", test_results::comparison_report(&runs, &differences)),
+        TestFormat::Junit => print!("{}", test_results::junit_report(&runs)),
+        TestFormat::Json => println!("{}", test_results::json_report(&runs, &differences)),
+    }
+    if !differences.is_empty() {
+        return Err(anyhow!("Test results differ between targets"));
+    }
+    match passed {
+        true => Ok(()),
+        false => Err(anyhow!("Tests failed")),
+    }
+}
 
-    match target {
+/// Compiles the tests for `target`, returning the command that runs them.
+fn build_tests(
+    path: &Path,
+    filter: Option<&str>,
+    target: &Target,
+    context: &BuildContext,
+) -> Result<(Command, SourceMaps)> {
+    let mut program = parse_program(path)?;
+    context.clean()?;
+    transform_tests(path, &mut program, filter, target)?;
+
+    let (command, source_maps) = match target {
         Target::Go => {
-            let mut identifier_transformer =
-                transformers::GoIdentifierTransformer::new(path.into());
-            identifier_transformer.transform(&mut program);
+            let mut compiler = GoTarget::new(path.into());
+            let source_maps = compiler.compile(program, context.out_dir())?;
 
-            let mut compiler = GoTarget::new();
-            let compile_dir = PathBuf::from(".dist/runtime");
-            std::fs::create_dir_all(&compile_dir)?;
-            compiler.compile(program, &compile_dir)?;
+            context.init_go_module()?;
+            let mut command = context.command("go");
+            command.arg("run").arg(".");
+            (command, source_maps)
+        }
+        Target::Js => {
+            let mut compiler = JsTarget::new(path.into());
+            let source_maps = compiler.compile(program, context.out_dir(), true)?;
 
-            std::env::set_current_dir(compile_dir)?;
-            let _ = Command::new("go")
-                .arg("mod")
-                .arg("init")
-                .arg("fsf")
-                .output()?;
+            let mut command = context.command("node");
+            command.arg("--enable-source-maps").arg("main.js");
+            (command, source_maps)
+        }
+    };
+    Ok((command, source_maps))
+}
 
-            match Command::new("go").arg("run").arg(".").status()?.success() {
-                true => Ok(()),
-                false => Err(anyhow!("Tests failed")),
+fn fmt(path: &Path, check: bool) -> Result<()> {
+    let mut unformatted = 0;
+    for file in fsf_files(path) {
+        let source = std::fs::read_to_string(&file)?;
+        let formatted = formatter::format_source(&source, &file)?;
+        if formatted == source {
+            continue;
+        }
+        match check {
+            true => {
+                println!("{} is not formatted", file.display());
+                unformatted += 1;
             }
+            false => std::fs::write(&file, formatted)?,
         }
-        Target::Js => {
-            let mut identifier_transformer =
-                transformers::JsIdentifierTransformer::new(path.into());
-            identifier_transformer.transform(&mut program);
+    }
+
+    match unformatted {
+        0 => Ok(()),
+        _ => Err(anyhow!("{} file(s) need formatting", unformatted)),
+    }
+}
 
-            let mut compiler = JsTarget::new();
-            let compile_dir = PathBuf::from(".dist/js");
-            std::fs::create_dir_all(&compile_dir)?;
-            compiler.compile(program, &compile_dir, true)?;
+fn check(path: &Path, message_format: &MessageFormat) -> Result<()> {
+    let project_dir = match path.is_dir() {
+        true => path,
+        false => path.parent().unwrap_or(Path::new(".")),
+    };
+    let load = |path: &Path| {
+        std::fs::read_to_string(path)
+            .ok()
+            .map(|source| ModuleAnalysis::new(path.to_path_buf(), &source))
+    };
+    let mut project = fsf_files(project_dir)
+        .into_iter()
+        .map(|path| load(&path).ok_or(anyhow!("Failed to read {}", path.display())))
+        .collect::<Result<Vec<_>>>()?;
+    project.sort_by(|a, b| a.path.cmp(&b.path));
+
+    let mut diagnostics = Vec::new();
+    for module in &project {
+        if path.is_file() && module.path != path {
+            continue;
+        }
+        diagnostics.extend(
+            module
+                .diagnostics
+                .iter()
+                .cloned()
+                .chain(module.check(&project, load))
+                .sorted_by_key(|diagnostic| (diagnostic.line, diagnostic.column))
+                .map(|diagnostic| (&module.path, diagnostic)),
+        );
+    }
 
-            std::env::set_current_dir(compile_dir)?;
-            match Command::new("node").arg("main.js").status()?.success() {
-                true => Ok(()),
-                false => Err(anyhow!("Tests failed")),
-            }
+    // Columns are reported from 1, like the toolchains do
+    for (path, diagnostic) in &diagnostics {
+        match message_format {
+            MessageFormat::Human => eprintln!(
+                "{}:{}:{}: {}: {}",
+                path.display(),
+                diagnostic.line,
+                diagnostic.column + 1,
+                diagnostic.severity,
+                diagnostic.message
+            ),
+            MessageFormat::Json => println!(
+                "{}",
+                serde_json::json!({
+                    "file": path,
+                    "line": diagnostic.line,
+                    "column": diagnostic.column + 1,
+                    "end_column": diagnostic.column + diagnostic.length + 1,
+                    "severity": diagnostic.severity.to_string(),
+                    "message": diagnostic.message,
+                })
+            ),
         }
     }
+
+    let errors = diagnostics
+        .iter()
+        .filter(|(_, diagnostic)| diagnostic.severity == Severity::Error)
+        .count();
+    match errors {
+        0 => Ok(()),
+        _ => Err(anyhow!(
+            "Found {} error(s) and {} warning(s)",
+            errors,
+            diagnostics.len() - errors
+        )),
+    }
 }
 
-fn setup_runtime() -> Result<(), std::io::Error> {
-    let output = Command::new("cp")
-        .arg("-R")
-        .arg("../runtime")
-        .arg("./.dist")
-        .output()?;
+/// Writes the documentation of a project and of the standard library, from the `///`
+/// comments of their items.
+fn doc(path: &Path, context: &BuildContext) -> Result<()> {
+    let mut program = parse_program(path)?;
+    program.extend(transformers::StandardLibraryTransformer::new(path.into()).modules()?);
+
+    context.clean()?;
+    documentation::generate(path, &program, context.out_dir())?;
+    println!(
+        "Documented {} module(s) in {}",
+        program.len(),
+        context.out_dir().join("index.html").display()
+    );
+    Ok(())
+}
 
-    if !output.status.success() {
-        eprintln!(
-            "Failed to copy directory: {}",
-            String::from_utf8_lossy(&output.stderr)
-        );
-        return Err(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Command execution failed",
-        ));
+/// Prints the output of a compiler stage. The transformed program and the generated
+/// source are produced the way the command of `pipeline` produces them.
+fn dump(
+    path: &Path,
+    project_dir: &Path,
+    stage: &Stage,
+    pipeline: &Pipeline,
+    target: &Target,
+    context: &BuildContext,
+) -> Result<()> {
+    let mut program = match path.is_dir() {
+        true => parse_program(path)?,
+        false => vec![parse_module_from_file(path.into())?],
     };
+
+    let target = match stage {
+        Stage::Tokens => {
+            for module in &program {
+                println!("// {}", module.path.display());
+                let mut scanner = Scanner::new(std::fs::read_to_string(&module.path)?);
+                for token in scanner.scan_tokens() {
+                    println!(
+                        "{}:{} {:?} {}",
+                        token.line, token.column, token.token_type, token.lexeme
+                    );
+                }
+            }
+            return Ok(());
+        }
+        Stage::Ast => {
+            println!("{:#?}", program);
+            return Ok(());
+        }
+        Stage::Transformed => target,
+        Stage::Go => &Target::Go,
+        Stage::Js => &Target::Js,
+    };
+
+    match pipeline {
+        Pipeline::Web => transform_web(project_dir, &mut program)?,
+        Pipeline::Run => transform_run(&mut program)?,
+        Pipeline::Test => transform_tests(project_dir, &mut program, None, target)?,
+    }
+    if let Stage::Transformed = stage {
+        println!("{:#?}", program);
+        return Ok(());
+    }
+
+    context.clean()?;
+    let source_maps = match target {
+        Target::Go => GoTarget::new(project_dir.into()).compile(program, context.out_dir())?,
+        Target::Js => {
+            let is_exec = !matches!(pipeline, Pipeline::Web);
+            JsTarget::new(project_dir.into()).compile(program, context.out_dir(), is_exec)?
+        }
+    };
+    let out_dir = context.out_dir().canonicalize()?;
+    for generated in source_maps.keys().sorted() {
+        let name = generated.strip_prefix(&out_dir).unwrap_or(generated);
+        println!("// {}", name.display());
+        print!("{}", std::fs::read_to_string(generated)?);
+    }
+    Ok(())
+}
+
+/// Writes files that are built into the compiler to a directory.
+fn write_files(dir: &Path, files: &[(&str, &str)]) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (file_name, contents) in files {
+        std::fs::write(dir.join(file_name), contents)?;
+    }
     Ok(())
 }
@@ -1,11 +1,12 @@
 use crate::expression::{
-    BlockExpression, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk,
-    LambdaParameter,
+    Align, BlockExpression, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk,
+    FormatSpec, LambdaParameter,
 };
-use crate::item::{Item, Parameter, StructField};
-use crate::statement::{Declaration, MaybeStatement, Statement};
+use crate::item::{Attribute, Item, Parameter, StructField};
+use crate::scanner::Scanner;
+use crate::statement::{Asserted, Declaration, MaybeStatement, Statement, TestOptions};
 use crate::token::{Literal, Token, TokenType};
-use std::path::PathBuf;
+use std::collections::HashMap;
 use thiserror::Error;
 
 #[derive(Debug, PartialEq, Copy, Clone)]
@@ -20,36 +21,81 @@ pub enum ParseError {
     SyntaxError(Token, String),
 }
 
+impl ParseError {
+    /// The token the error was found at.
+    pub fn token(&self) -> &Token {
+        match self {
+            Self::SyntaxError(token, _) => token,
+        }
+    }
+}
+
 pub struct Parser {
     tokens: Vec<Token>,
     current: usize,
-    path: PathBuf,
     context_stack: Vec<ParseContext>,
+    /// The doc comments before each token. They are taken out of the tokens so that
+    /// only items and struct fields look at them, and are ignored anywhere else.
+    doc_comments: HashMap<usize, String>,
 }
 
 impl Parser {
-    pub fn new(tokens: Vec<Token>, path: PathBuf) -> Parser {
+    pub fn new(tokens: Vec<Token>) -> Parser {
+        let mut doc_comments = HashMap::new();
+        let mut kept = Vec::with_capacity(tokens.len());
+        for token in tokens {
+            match (&token.token_type, &token.value) {
+                (TokenType::DocComment, Some(Literal::String(text))) => {
+                    doc_comments
+                        .entry(kept.len())
+                        .and_modify(|doc: &mut String| {
+                            doc.push('\n');
+                            doc.push_str(text);
+                        })
+                        .or_insert_with(|| text.clone());
+                }
+                _ => kept.push(token),
+            }
+        }
+
         Self {
-            tokens,
-            path,
+            tokens: kept,
             current: 0,
             context_stack: Vec::new(),
+            doc_comments,
         }
     }
 
-    pub fn parse(&mut self) -> Vec<Item> {
+    /// Parses every item, carrying on after a syntax error so that the errors in all
+    /// of the module's items are reported together.
+    pub fn parse(&mut self) -> Result<Vec<Item>, Vec<ParseError>> {
         let mut items = Vec::new();
+        let mut errors = Vec::new();
         while !self.is_at_end() {
-            if let Some(item) = self.item() {
-                items.push(item)
+            let start = self.current;
+            match self.item() {
+                Ok(item) => items.push(item),
+                Err(error) => {
+                    errors.push(error);
+                    self.synchronize(start);
+                }
             }
         }
-        items
+
+        match errors.is_empty() {
+            true => Ok(items),
+            false => Err(errors),
+        }
     }
 
-    fn item(&mut self) -> Option<Item> {
-        let item = if self.match_token(&[TokenType::Fn, TokenType::Cmpnt]) {
+    fn item(&mut self) -> Result<Item, ParseError> {
+        let doc = self.doc_comment();
+        let item = if self.check(&TokenType::Hash) {
+            self.attributed_item()
+        } else if self.match_token(&[TokenType::Fn, TokenType::Cmpnt]) {
             self.function()
+        } else if self.match_token(&[TokenType::Loader]) {
+            self.loader()
         } else if self.match_token(&[TokenType::Import]) {
             self.import()
         } else if self.match_token(&[TokenType::TestRunner]) {
@@ -63,13 +109,114 @@ impl Parser {
                 "Expected item declaration".to_string(),
             ))
         };
+        Ok(item?.with_doc(doc))
+    }
+
+    /// The doc comment before the current token.
+    fn doc_comment(&mut self) -> Option<String> {
+        self.doc_comments.remove(&self.current)
+    }
+
+    fn attributed_item(&mut self) -> Result<Item, ParseError> {
+        let mut attributes = Vec::new();
+        while self.match_token(&[TokenType::Hash]) {
+            self.consume(TokenType::LeftSquareBracket, "Expect '[' after '#'")?;
+            let name = self
+                .consume(TokenType::Identifier, "Expect attribute name")?
+                .clone();
+            let value = match self.match_token(&[TokenType::Equal]) {
+                true => match &self
+                    .consume(TokenType::String, "Expect string after '='")?
+                    .value
+                {
+                    Some(Literal::String(value)) => Some(value.clone()),
+                    _ => return Err(self.unexpected()),
+                },
+                false => None,
+            };
+            self.consume(TokenType::RightSquareBracket, "Expect ']' after attribute")?;
+            attributes.push(Attribute { name, value });
+        }
 
-        match item {
-            Ok(item) => Some(item),
-            Err(error) => {
-                // TODO: Without synchronization we can get in an infinite loop here
-                panic!("Got error {}: {:?}", self.path.to_string_lossy(), error);
+        let is_known = |attribute: &&Attribute| {
+            ["server", "test", "ignore", "should_panic"].contains(&attribute.name.lexeme.as_str())
+        };
+        if let Some(attribute) = attributes.iter().find(|a| !is_known(a)) {
+            return Err(ParseError::SyntaxError(
+                attribute.name.clone(),
+                format!("Unknown attribute '{}'", attribute.name.lexeme),
+            ));
+        }
+
+        let is_server = Attribute::find(&attributes, "server").is_some();
+        let message = match is_server {
+            true => "Expect function after '#[server]'",
+            false => "Expect function after attribute",
+        };
+        let doc = self.doc_comment();
+        self.consume(TokenType::Fn, message)?;
+        match self.function()? {
+            Item::Function {
+                name,
+                type_parameters,
+                parameters,
+                body,
+                return_type,
+                ..
+            } if is_server => {
+                if let Some(attribute) = attributes.iter().find(|a| a.name.lexeme != "server") {
+                    return Err(ParseError::SyntaxError(
+                        attribute.name.clone(),
+                        format!(
+                            "Server functions cannot have the attribute '{}'",
+                            attribute.name.lexeme
+                        ),
+                    ));
+                }
+                match type_parameters.is_empty() {
+                    true => Ok(Item::Action {
+                        name,
+                        parameters,
+                        body,
+                        return_type,
+                        doc,
+                    }),
+                    false => Err(ParseError::SyntaxError(
+                        self.previous().clone(),
+                        "Server functions cannot have type parameters".to_string(),
+                    )),
+                }
+            }
+            Item::Function {
+                name,
+                type_parameters,
+                parameters,
+                body,
+                return_type,
+                ..
+            } => {
+                let is_test =
+                    name.starts_with("test_") || Attribute::find(&attributes, "test").is_some();
+                if let Some(attribute) = attributes.first().filter(|_| !is_test) {
+                    return Err(ParseError::SyntaxError(
+                        attribute.name.clone(),
+                        format!(
+                            "'#[{}]' can only be used on tests, which are named 'test_*' or marked '#[test]'",
+                            attribute.name.lexeme
+                        ),
+                    ));
+                }
+                Ok(Item::Function {
+                    name,
+                    type_parameters,
+                    parameters,
+                    body,
+                    return_type,
+                    attributes,
+                    doc,
+                })
             }
+            _ => Err(self.unexpected()),
         }
     }
 
@@ -83,6 +230,22 @@ impl Parser {
             )),
         }?;
 
+        let mut type_parameters = Vec::new();
+        if self.match_token(&[TokenType::Less]) {
+            loop {
+                let type_parameter = self
+                    .consume(TokenType::Identifier, "Expect type parameter name")?
+                    .lexeme
+                    .clone();
+                type_parameters.push(type_parameter);
+
+                if !self.match_token(&[TokenType::Comma]) {
+                    break;
+                }
+            }
+            self.consume(TokenType::Greater, "Expected '>' after type parameters")?;
+        }
+
         self.consume(TokenType::LeftParen, "Expected '('")?;
         let mut parameters = Vec::new();
         if !self.check(&TokenType::RightParen) {
@@ -92,10 +255,7 @@ impl Parser {
                     .lexeme
                     .clone();
                 self.consume(TokenType::Colon, "Expect type annotation")?;
-                let type_annotation = self
-                    .consume(TokenType::Identifier, "Expect type annotation")?
-                    .lexeme
-                    .clone();
+                let type_annotation = self.type_annotation("Expect type annotation")?;
                 parameters.push(Parameter {
                     name,
                     type_annotation,
@@ -111,22 +271,7 @@ impl Parser {
         let return_type = match token.token_type {
             TokenType::Fn => {
                 if self.match_token(&[TokenType::MinusGreater]) {
-                    // TODO: Remove allowing strings. This is just to help with compiling to Go
-                    // for now.
-                    match self.match_token(&[TokenType::Identifier, TokenType::String]) {
-                        true => {
-                            let previous = self.previous().clone();
-                            match previous.token_type {
-                                TokenType::Identifier => Ok(Some(previous.lexeme)),
-                                TokenType::String => Ok(Some(previous.value.unwrap().to_string())),
-                                _ => panic!("Impossible to reach"),
-                            }
-                        }
-                        false => Err(ParseError::SyntaxError(
-                            self.previous().clone(),
-                            "Expected return type".to_string(),
-                        )),
-                    }?
+                    Some(self.type_annotation("Expected return type")?)
                 } else {
                     None
                 }
@@ -139,19 +284,65 @@ impl Parser {
         match token.token_type {
             TokenType::Fn => Ok(Item::Function {
                 name,
+                type_parameters,
                 parameters,
                 body,
                 return_type,
+                attributes: Vec::new(),
+                doc: None,
             }),
             TokenType::Cmpnt => Ok(Item::Component {
                 name,
                 parameters,
                 body,
+                doc: None,
             }),
-            _ => panic!("Expected function or component"),
+            _ => Err(ParseError::SyntaxError(
+                token,
+                "Expected function or component".to_string(),
+            )),
         }
     }
 
+    fn type_annotation(&mut self, message: &str) -> Result<String, ParseError> {
+        // TODO: Remove allowing strings. This is just to help with compiling to Go
+        // for now.
+        match self.match_token(&[TokenType::Identifier, TokenType::String]) {
+            true => {
+                let previous = self.previous().clone();
+                match previous.token_type {
+                    TokenType::Identifier => Ok(previous.lexeme),
+                    TokenType::String => Ok(previous.value.unwrap().to_string()),
+                    _ => Err(self.unexpected()),
+                }
+            }
+            false => Err(ParseError::SyntaxError(
+                self.previous().clone(),
+                message.to_string(),
+            )),
+        }
+    }
+
+    fn loader(&mut self) -> Result<Item, ParseError> {
+        let name = self
+            .consume(TokenType::Identifier, "Expect component name")?
+            .lexeme
+            .clone();
+        self.consume(TokenType::LeftParen, "Expected '('")?;
+        self.consume(TokenType::RightParen, "Loaders do not take parameters")?;
+        self.consume(TokenType::MinusGreater, "Expect loader return type")?;
+        let return_type = self.type_annotation("Expected return type")?;
+        self.consume(TokenType::LeftBrace, "Expected '{'")?;
+        let body = self.block_expression()?;
+
+        Ok(Item::Loader {
+            name,
+            body,
+            return_type,
+            doc: None,
+        })
+    }
+
     fn import(&mut self) -> Result<Item, ParseError> {
         let mut path = Vec::new();
 
@@ -180,6 +371,7 @@ impl Parser {
         self.consume(TokenType::LeftBrace, "Expect '{'")?;
         let mut fields = Vec::new();
         while !self.check(&TokenType::RightBrace) {
+            let doc = self.doc_comment();
             let name = self
                 .consume(TokenType::Identifier, "Expect field name")?
                 .clone()
@@ -194,12 +386,17 @@ impl Parser {
             fields.push(StructField {
                 name,
                 type_annotation,
+                doc,
             });
         }
 
         self.consume(TokenType::RightBrace, "Expect '}'")?;
 
-        Ok(Item::Struct { name, fields })
+        Ok(Item::Struct {
+            name,
+            fields,
+            doc: None,
+        })
     }
 
     fn lambda(&mut self) -> Result<ExpressionWithoutBlock, ParseError> {
@@ -264,7 +461,10 @@ impl Parser {
         if self.match_token(&[
             TokenType::Let,
             TokenType::Print,
+            TokenType::Assert,
             TokenType::AssertEq,
+            TokenType::AssertNe,
+            TokenType::Panic,
             TokenType::RunTest,
         ]) {
             match self.previous().token_type {
@@ -272,17 +472,40 @@ impl Parser {
 
                 // TODO: Remove these as builtins
                 TokenType::Print => Ok(MaybeStatement::Statement(self.print_statement()?)),
-                TokenType::AssertEq => Ok(MaybeStatement::Statement(self.assert_eq_statement()?)),
+                TokenType::Assert => Ok(MaybeStatement::Statement(self.assert_statement()?)),
+                TokenType::AssertEq | TokenType::AssertNe => {
+                    Ok(MaybeStatement::Statement(self.assert_eq_statement()?))
+                }
+                TokenType::Panic => Ok(MaybeStatement::Statement(self.panic_statement()?)),
                 TokenType::RunTest => Ok(MaybeStatement::Statement(self.run_test_statement()?)),
-                ref t => panic!("Unexpected statement type {:?}", t),
+                _ => Err(self.unexpected()),
             }
         } else {
             Ok(MaybeStatement::Expression(self.expression()?))
         }
     }
 
-    fn synchronize(&mut self) {
-        todo!();
+    /// Skips to the start of the next item after a syntax error in the item that
+    /// started at `start`.
+    fn synchronize(&mut self, start: usize) {
+        self.context_stack.clear();
+        if self.current == start {
+            self.advance();
+        }
+        while !self.is_at_end()
+            && !matches!(
+                self.peek().token_type,
+                TokenType::Fn
+                    | TokenType::Cmpnt
+                    | TokenType::Loader
+                    | TokenType::Import
+                    | TokenType::Struct
+                    | TokenType::Hash
+                    | TokenType::TestRunner
+            )
+        {
+            self.advance();
+        }
     }
 
     fn let_declaration(&mut self) -> Result<Statement, ParseError> {
@@ -327,7 +550,7 @@ impl Parser {
                         self.consume(TokenType::RightParen, "Expect ')'")?;
                         Declaration::Tuple(names)
                     }
-                    _ => panic!("Not possible"),
+                    _ => return Err(self.unexpected()),
                 },
                 false => {
                     let name = self
@@ -345,6 +568,7 @@ impl Parser {
             declaration,
             expression: initializer,
             mutable,
+            number_type: None,
         })
     }
 
@@ -356,14 +580,62 @@ impl Parser {
         Ok(Statement::Print(value))
     }
 
+    fn assert_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '('")?;
+        let condition = self.asserted()?;
+        let message = self.assertion_message()?;
+        self.consume(TokenType::Semicolon, "Expect ';'")?;
+        Ok(Statement::Assert { condition, message })
+    }
+
+    /// Parses `assert_eq` and `assert_ne`, whichever was just matched.
     fn assert_eq_statement(&mut self) -> Result<Statement, ParseError> {
+        let is_eq = self.previous().token_type == TokenType::AssertEq;
         self.consume(TokenType::LeftParen, "Expect '('")?;
-        let left = self.expression()?;
+        let left = self.asserted()?;
         self.consume(TokenType::Comma, "Expect ','")?;
-        let right = self.expression()?;
+        let right = self.asserted()?;
+        let message = self.assertion_message()?;
+        self.consume(TokenType::Semicolon, "Expect ';'")?;
+        Ok(match is_eq {
+            true => Statement::AssertEq {
+                left,
+                right,
+                message,
+            },
+            false => Statement::AssertNe {
+                left,
+                right,
+                message,
+            },
+        })
+    }
+
+    fn asserted(&mut self) -> Result<Asserted, ParseError> {
+        let start = self.current;
+        let expression = self.expression()?;
+        Ok(Asserted {
+            expression: Box::new(expression),
+            source: source_text(&self.tokens[start..self.current]),
+        })
+    }
+
+    /// Parses the optional message after the asserted expressions, and the closing ')'.
+    fn assertion_message(&mut self) -> Result<Option<Expression>, ParseError> {
+        let message = match self.match_token(&[TokenType::Comma]) {
+            true => Some(self.expression()?),
+            false => None,
+        };
+        self.consume(TokenType::RightParen, "Expect ')'")?;
+        Ok(message)
+    }
+
+    fn panic_statement(&mut self) -> Result<Statement, ParseError> {
+        self.consume(TokenType::LeftParen, "Expect '('")?;
+        let message = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')'")?;
         self.consume(TokenType::Semicolon, "Expect ';'")?;
-        Ok(Statement::AssertEq(left, right))
+        Ok(Statement::Panic(message))
     }
 
     fn run_test_statement(&mut self) -> Result<Statement, ParseError> {
@@ -371,12 +643,42 @@ impl Parser {
         let test_name = self.consume(TokenType::String, "Expect test name")?.clone();
         self.consume(TokenType::Comma, "Expect ',' after test name")?;
         let function_name = Box::new(self.expression()?);
+
+        // Followed by options like `setup = setup` or `ignore = "reason"`
+        let mut options = TestOptions::default();
+        while self.match_token(&[TokenType::Comma]) {
+            let option = self
+                .consume(TokenType::Identifier, "Expect test option")?
+                .clone();
+            self.consume(TokenType::Equal, "Expect '=' after test option")?;
+            match option.lexeme.as_str() {
+                "setup" => options.setup = Some(Box::new(self.expression()?)),
+                "teardown" => options.teardown = Some(Box::new(self.expression()?)),
+                "ignore" | "should_panic" => {
+                    let value = match &self.consume(TokenType::String, "Expect string")?.value {
+                        Some(Literal::String(value)) => value.clone(),
+                        _ => return Err(self.unexpected()),
+                    };
+                    match option.lexeme.as_str() {
+                        "ignore" => options.ignore = Some(value),
+                        _ => options.should_panic = Some(value),
+                    }
+                }
+                _ => {
+                    return Err(ParseError::SyntaxError(
+                        option.clone(),
+                        format!("Unknown test option '{}'", option.lexeme),
+                    ))
+                }
+            }
+        }
         self.consume(TokenType::RightParen, "Expect ')'")?;
         self.consume(TokenType::Semicolon, "Expect ';'")?;
 
         Ok(Statement::RunTest {
             test_name,
             function_name,
+            options,
         })
     }
 
@@ -392,23 +694,25 @@ impl Parser {
         match self.previous().token_type {
             TokenType::LeftBrace => Ok(ExpressionWithBlock::Block(self.block_expression()?.into())),
             TokenType::If => self.if_expression(),
-            ref t => {
-                panic!("Unexpected token for expression with block: {:?}", t);
-            }
+            _ => Err(self.unexpected()),
         }
     }
 
     fn block_expression(&mut self) -> Result<BlockExpression, ParseError> {
+        let line = self.previous().line;
         let mut expr: Option<ExpressionWithoutBlock> = None;
-        let mut statements: Vec<Statement> = Vec::new();
+        let mut statements: Vec<(usize, Statement)> = Vec::new();
 
         while !self.match_token(&[TokenType::RightBrace]) {
+            let statement_line = self.peek().line;
             match self.maybe_statement()? {
-                MaybeStatement::Statement(statement) => statements.push(statement),
+                MaybeStatement::Statement(statement) => {
+                    statements.push((statement_line, statement))
+                }
                 MaybeStatement::Expression(expression) => {
                     if self.check(&TokenType::Semicolon) {
                         self.advance();
-                        statements.push(Statement::Expression(expression));
+                        statements.push((statement_line, Statement::Expression(expression)));
                     } else {
                         match expression {
                             Expression::WithoutBlock(expression) => {
@@ -421,7 +725,10 @@ impl Parser {
                                 }
                             }
                             Expression::WithBlock(e) => {
-                                statements.push(Statement::Expression(Expression::WithBlock(e)));
+                                statements.push((
+                                    statement_line,
+                                    Statement::Expression(Expression::WithBlock(e)),
+                                ));
                             }
                         }
                     }
@@ -429,7 +736,11 @@ impl Parser {
             }
         }
 
-        Ok(BlockExpression { statements, expr })
+        Ok(BlockExpression {
+            statements,
+            expr,
+            line,
+        })
     }
 
     fn if_expression(&mut self) -> Result<ExpressionWithBlock, ParseError> {
@@ -475,6 +786,7 @@ impl Parser {
                     name,
                     value: value.into(),
                     operator,
+                    number_type: None,
                 });
             }
 
@@ -494,6 +806,7 @@ impl Parser {
                 left: expr.into(),
                 operator,
                 right: right.into(),
+                number_type: None,
             }
         }
 
@@ -520,6 +833,7 @@ impl Parser {
                 left: expr.into(),
                 operator,
                 right: right.into(),
+                number_type: None,
             }
         }
 
@@ -536,6 +850,7 @@ impl Parser {
                 left: expr.into(),
                 operator,
                 right: right.into(),
+                number_type: None,
             }
         }
 
@@ -552,6 +867,7 @@ impl Parser {
                 left: expr.into(),
                 operator,
                 right: right.into(),
+                number_type: None,
             }
         }
 
@@ -565,6 +881,7 @@ impl Parser {
             return Ok(ExpressionWithoutBlock::Unary {
                 operator,
                 right: right.into(),
+                number_type: None,
             });
         }
 
@@ -726,7 +1043,10 @@ impl Parser {
             "Expect ']' after array elements",
         )?;
 
-        Ok(ExpressionWithoutBlock::Array { elements })
+        Ok(ExpressionWithoutBlock::Array {
+            elements,
+            element_type: None,
+        })
     }
 
     fn tuple(&mut self) -> Result<ExpressionWithoutBlock, ParseError> {
@@ -761,59 +1081,118 @@ impl Parser {
         Ok(ExpressionWithoutBlock::Struct { name, fields })
     }
 
+    /// Parses the expressions between the braces of an f-string from the text of the
+    /// string, which keeps its escape sequences so that they don't end an expression's
+    /// own strings.
     fn fstring(&mut self) -> Result<ExpressionWithoutBlock, ParseError> {
-        let string = match &self.previous().value {
-            Some(Literal::String(s)) => s,
-            _ => panic!("fstring needs a literal string value"),
+        let token = self.previous().clone();
+        let text = match &token.value {
+            Some(Literal::String(s)) => s.clone(),
+            _ => return Err(self.unexpected()),
         };
+        let error = |message: &str| ParseError::SyntaxError(token.clone(), message.to_string());
 
         let mut chunks = Vec::new();
-        let mut current_position = 0;
-        let mut current_literal = String::new();
-
-        let chars: Vec<char> = string.chars().collect();
-
-        while current_position < chars.len() {
-            if chars[current_position] == '{' {
-                // Save any accumulated literal before the opening brace
-                if !current_literal.is_empty() {
-                    chunks.push(FStringChunk::Literal(current_literal.clone()));
-                    current_literal.clear();
+        let mut literal = String::new();
+        let mut current = 0;
+        while let Some(c) = text[current..].chars().next() {
+            let next = text[current + c.len_utf8()..].chars().next();
+            match (c, next) {
+                ('{', Some('{')) | ('}', Some('}')) => {
+                    literal.push(c);
+                    current += 2;
                 }
-
-                current_position += 1; // Move past the '{'
-                let start_position = current_position;
-
-                // Find the closing brace
-                while current_position < chars.len() && chars[current_position] != '}' {
-                    current_position += 1;
-                }
-
-                if current_position < chars.len() {
-                    let identifier: String =
-                        chars[start_position..current_position].iter().collect();
-                    if !identifier.is_empty() {
-                        chunks.push(FStringChunk::Identifier(identifier));
+                ('{', _) => {
+                    let start = current + 1;
+                    let (end, colon) = embedded_expression_end(&text, start)
+                        .ok_or_else(|| error("Expect '}' after the f-string expression"))?;
+                    if !literal.is_empty() {
+                        chunks.push(FStringChunk::Literal(self.unescape(&token, &literal)?));
+                        literal.clear();
                     }
-                    current_position += 1; // Move past the '}'
-                } else {
-                    // Unclosed brace, treat the '{' as a literal
-                    current_literal.push('{');
+                    let format = match colon {
+                        Some(colon) => format_spec(&text[colon + 1..end])
+                            .ok_or_else(|| error("Invalid format specifier"))?,
+                        None => FormatSpec::default(),
+                    };
+                    let expression =
+                        self.embedded_expression(&token, &text, start, colon.unwrap_or(end))?;
+                    chunks.push(FStringChunk::Expression {
+                        expression: Box::new(expression),
+                        format,
+                        number_type: None,
+                    });
+                    current = end + 1;
+                }
+                ('}', _) => return Err(error("Unmatched '}' in f-string, use '}}' for a brace")),
+                // Escaped characters are kept for `unescape`
+                ('\\', Some(next)) => {
+                    literal.push(c);
+                    literal.push(next);
+                    current += 1 + next.len_utf8();
+                }
+                _ => {
+                    literal.push(c);
+                    current += c.len_utf8();
                 }
-            } else {
-                current_literal.push(chars[current_position]);
-                current_position += 1;
             }
         }
 
-        // Add any remaining literal
-        if !current_literal.is_empty() {
-            chunks.push(FStringChunk::Literal(current_literal));
+        if !literal.is_empty() {
+            chunks.push(FStringChunk::Literal(self.unescape(&token, &literal)?));
         }
 
         Ok(ExpressionWithoutBlock::FString { chunks })
     }
 
+    fn unescape(&self, token: &Token, text: &str) -> Result<String, ParseError> {
+        Scanner::unescape(text)
+            .map_err(|error| ParseError::SyntaxError(token.clone(), error.message))
+    }
+
+    /// Parses the expression at `start..end` of the text of an f-string. Its tokens are
+    /// moved to where they are in the source.
+    fn embedded_expression(
+        &self,
+        token: &Token,
+        text: &str,
+        start: usize,
+        end: usize,
+    ) -> Result<Expression, ParseError> {
+        let mut scanner = Scanner::new(text[start..end].to_string());
+        let mut tokens = scanner.scan_tokens();
+        if let Some(error) = scanner.errors().first() {
+            return Err(ParseError::SyntaxError(
+                token.clone(),
+                error.message.clone(),
+            ));
+        }
+
+        let before = &text[..start];
+        let line = token.line + before.matches('\n').count();
+        let column = match before.rfind('\n') {
+            Some(newline) => start - newline - 1,
+            // After the backtick
+            None => token.column + 1 + start,
+        };
+        for embedded in &mut tokens {
+            if embedded.line == 1 {
+                embedded.column += column;
+            }
+            embedded.line += line - 1;
+        }
+
+        let mut parser = Parser::new(tokens);
+        let expression = parser.expression()?;
+        match parser.is_at_end() {
+            true => Ok(expression),
+            false => Err(ParseError::SyntaxError(
+                parser.peek().clone(),
+                "Expect '}' after the f-string expression".to_string(),
+            )),
+        }
+    }
+
     fn html(&mut self) -> Result<ExpressionWithoutBlock, ParseError> {
         let name = self
             .consume(TokenType::Identifier, "Expect identifier")?
@@ -856,7 +1235,7 @@ impl Parser {
 
         Ok(ExpressionWithoutBlock::Html {
             name,
-            inner: inner.into(),
+            inner,
             attributes,
         })
     }
@@ -909,8 +1288,15 @@ impl Parser {
         self.tokens.get(self.current - 1).unwrap()
     }
 
+    /// An error at a token that was matched where the parser doesn't handle it.
+    fn unexpected(&self) -> ParseError {
+        let token = self.previous().clone();
+        let message = format!("Unexpected '{}'", token.lexeme);
+        ParseError::SyntaxError(token, message)
+    }
+
     fn is_at_end(&self) -> bool {
-        self.peek().token_type == TokenType::EOF
+        self.peek().token_type == TokenType::Eof
     }
 
     fn push_context(&mut self, context: ParseContext) {
@@ -928,3 +1314,81 @@ impl Parser {
             .unwrap_or(ParseContext::Normal)
     }
 }
+
+/// Reconstructs the source of `tokens` from their positions. Spacing within a line is
+/// kept and line breaks become a single space.
+fn source_text(tokens: &[Token]) -> String {
+    let mut text = String::new();
+    let mut previous: Option<&Token> = None;
+    for token in tokens {
+        if let Some(previous) = previous {
+            let previous_end = previous.column + previous.lexeme.len();
+            match token.line == previous.line {
+                true => text.push_str(&" ".repeat(token.column.saturating_sub(previous_end))),
+                false => text.push(' '),
+            }
+        }
+        text.push_str(&token.lexeme);
+        previous = Some(token);
+    }
+    text
+}
+
+/// The end of the f-string expression starting at `start`, which is the brace closing
+/// it, and the colon before its format specifier if it has one. Braces in the
+/// expression's blocks and strings don't close it.
+fn embedded_expression_end(text: &str, start: usize) -> Option<(usize, Option<usize>)> {
+    let mut depth = 0;
+    let mut colon = None;
+    let mut in_string = false;
+    let mut chars = text[start..].char_indices().map(|(i, c)| (start + i, c));
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' if in_string => {
+                chars.next();
+            }
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '{' | '(' | '[' => depth += 1,
+            '}' if depth == 0 => return Some((i, colon)),
+            '}' | ')' | ']' => depth -= 1,
+            ':' if depth == 0 && colon.is_none() => colon = Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parses a format specifier like `>8.2`, which is an alignment, a `0` to pad with
+/// zeros, a width and a precision, all optional.
+fn format_spec(spec: &str) -> Option<FormatSpec> {
+    let (align, spec) = match spec.chars().next() {
+        Some('<') => (Some(Align::Left), &spec[1..]),
+        Some('>') => (Some(Align::Right), &spec[1..]),
+        _ => (None, spec),
+    };
+    let (zero, spec) = match spec.strip_prefix('0') {
+        Some(spec) => (true, spec),
+        None => (false, spec),
+    };
+    let (width, precision) = match spec.split_once('.') {
+        Some((width, precision)) => (width, Some(precision)),
+        None => (spec, None),
+    };
+    let number = |digits: &str| match digits.chars().all(|c| c.is_ascii_digit()) {
+        true => digits.parse::<usize>().ok(),
+        false => None,
+    };
+    Some(FormatSpec {
+        align,
+        zero,
+        width: match width {
+            "" => None,
+            width => Some(number(width)?),
+        },
+        precision: match precision {
+            Some(precision) => Some(number(precision)?),
+            None => None,
+        },
+    })
+}
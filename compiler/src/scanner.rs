@@ -1,10 +1,21 @@
-use crate::token::{Literal, Token, TokenType};
+use crate::token::{Literal, NumberType, Token, TokenType};
+use thiserror::Error;
+
+#[derive(Error, Debug, Clone)]
+#[error("{message}")]
+pub struct ScanError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
 
 pub struct Scanner {
     source: String,
     start: usize,
     current: usize,
     line: usize,
+    keep_comments: bool,
+    errors: Vec<ScanError>,
 }
 
 impl Scanner {
@@ -14,6 +25,17 @@ impl Scanner {
             start: 0,
             current: 0,
             line: 1,
+            keep_comments: false,
+            errors: Vec::new(),
+        }
+    }
+
+    /// A scanner that emits comments as tokens, for tools that print source back out.
+    /// The parser doesn't expect them.
+    pub fn with_comments(source: String) -> Scanner {
+        Scanner {
+            keep_comments: true,
+            ..Scanner::new(source)
         }
     }
 
@@ -22,10 +44,16 @@ impl Scanner {
 
         while !self.is_at_end() {
             self.start = self.current;
+            let scanned = tokens.len();
             self.scan_token(&mut tokens);
+
+            let column = self.column();
+            for token in &mut tokens[scanned..] {
+                token.column = column;
+            }
         }
 
-        tokens.push(Token::new(TokenType::EOF, "".to_string(), None, self.line));
+        tokens.push(Token::new(TokenType::Eof, "".to_string(), None, self.line));
 
         tokens
     }
@@ -60,6 +88,7 @@ impl Scanner {
             '|' => tokens.push(Token::new(TokenType::Pipe, c.to_string(), None, self.line)),
             ',' => tokens.push(Token::new(TokenType::Comma, c.to_string(), None, self.line)),
             '.' => tokens.push(Token::new(TokenType::Dot, c.to_string(), None, self.line)),
+            '#' => tokens.push(Token::new(TokenType::Hash, c.to_string(), None, self.line)),
             '-' => {
                 if self.match_char('>') {
                     tokens.push(Token::new(
@@ -88,7 +117,7 @@ impl Scanner {
                 if self.match_char(':') {
                     tokens.push(Token::new(
                         TokenType::ColonColon,
-                        c.to_string(),
+                        "::".to_string(),
                         None,
                         self.line,
                     ));
@@ -180,9 +209,9 @@ impl Scanner {
             }
             '/' => {
                 if self.match_char('/') {
-                    while self.peek() != '\n' && !self.is_at_end() {
-                        self.advance();
-                    }
+                    self.line_comment(tokens);
+                } else if self.match_char('*') {
+                    self.block_comment(tokens);
                 } else if self.match_char('>') {
                     tokens.push(Token::new(
                         TokenType::SlashGreater,
@@ -202,6 +231,7 @@ impl Scanner {
             ' ' | '\r' | '\t' => (),
             '\n' => self.line += 1,
             '"' => self.string(tokens, false),
+            'r' if self.peek() == '"' => self.string(tokens, false),
             '`' => self.string(tokens, true),
             '0'..='9' => self.number(tokens),
             'a'..='z' | 'A'..='Z' | '_' => self.identifier(tokens),
@@ -218,13 +248,33 @@ impl Scanner {
                 self.line,
             )),
             '@' => self.raw_code(tokens),
-            _ => todo!("Handle unexpected tokens"),
+            _ => self.error(format!("Unexpected character '{}'", c)),
         }
     }
 
+    /// The errors found while scanning. The characters they were found at are skipped.
+    pub fn errors(&self) -> &[ScanError] {
+        &self.errors
+    }
+
+    fn error(&mut self, message: String) {
+        self.errors.push(ScanError {
+            line: self.line,
+            column: self.column(),
+            message,
+        });
+    }
+
+    /// The column of the token being scanned.
+    fn column(&self) -> usize {
+        let line_start = self.source[..self.start].rfind('\n').map_or(0, |i| i + 1);
+        self.start - line_start
+    }
+
+    // `current` is a byte offset into the source, so that tokens can be sliced out of it
     fn advance(&mut self) -> char {
-        let c = self.source.chars().nth(self.current).unwrap();
-        self.current += 1;
+        let c = self.peek();
+        self.current += c.len_utf8();
         c
     }
 
@@ -233,48 +283,123 @@ impl Scanner {
     }
 
     fn match_char(&mut self, expected: char) -> bool {
-        if self.is_at_end() || self.source.chars().nth(self.current).unwrap() != expected {
+        if self.is_at_end() || self.peek() != expected {
             return false;
         }
-        self.current += 1;
+        self.advance();
         true
     }
 
     fn peek(&self) -> char {
-        if self.is_at_end() {
-            return '\0';
-        }
-        self.source.chars().nth(self.current).unwrap()
+        self.source[self.current..].chars().next().unwrap_or('\0')
     }
 
     fn peek_next(&self) -> char {
-        if self.current + 1 >= self.source.len() {
-            return '\0';
+        self.source[self.current..].chars().nth(1).unwrap_or('\0')
+    }
+
+    /// A `//` comment, after its slashes. Comments starting with exactly three slashes
+    /// are doc comments, which are kept for the parser.
+    fn line_comment(&mut self, tokens: &mut Vec<Token>) {
+        while self.peek() != '\n' && !self.is_at_end() {
+            self.advance();
+        }
+        let text = self.source[self.start..self.current].trim_end();
+        let is_doc = text.starts_with("///") && !text.starts_with("////");
+        if self.keep_comments {
+            tokens.push(Token::new(
+                TokenType::Comment,
+                text.to_string(),
+                None,
+                self.line,
+            ));
+        } else if is_doc {
+            let doc = &text["///".len()..];
+            let doc = doc.strip_prefix(' ').unwrap_or(doc);
+            tokens.push(Token::new(
+                TokenType::DocComment,
+                text.to_string(),
+                Some(Literal::String(doc.to_string())),
+                self.line,
+            ));
         }
-        self.source.chars().nth(self.current + 1).unwrap()
     }
 
+    /// A `/* */` comment, after its `/*`. Block comments nest, so that code containing
+    /// them can be commented out.
+    fn block_comment(&mut self, tokens: &mut Vec<Token>) {
+        let line = self.line;
+        let mut depth = 1;
+        while depth > 0 && !self.is_at_end() {
+            match self.advance() {
+                '/' if self.peek() == '*' => {
+                    self.advance();
+                    depth += 1;
+                }
+                '*' if self.peek() == '/' => {
+                    self.advance();
+                    depth -= 1;
+                }
+                '\n' => self.line += 1,
+                _ => {}
+            }
+        }
+
+        if depth > 0 {
+            self.error("Unterminated block comment".to_string());
+        } else if self.keep_comments {
+            tokens.push(Token::new(
+                TokenType::Comment,
+                self.source[self.start..self.current].to_string(),
+                None,
+                line,
+            ));
+        }
+    }
+
+    /// Strings may span several lines. Their escape sequences are replaced by the
+    /// characters they stand for, except in raw strings like `r"C:\path"`. F-strings
+    /// keep theirs for the parser, which unescapes what's outside their expressions.
     fn string(&mut self, tokens: &mut Vec<Token>, is_fstring: bool) {
+        let line = self.line;
+        let is_raw = self.source[self.start..].starts_with('r');
+        if is_raw {
+            // Consume the opening "
+            self.advance();
+        }
         let terminator = match is_fstring {
             true => '`',
             false => '"',
         };
+
+        let mut value = String::new();
         while self.peek() != terminator && !self.is_at_end() {
-            if self.peek() == '\n' {
-                self.line += 1;
+            match self.advance() {
+                '\n' => {
+                    self.line += 1;
+                    value.push('\n');
+                }
+                '\\' if is_fstring && !self.is_at_end() => {
+                    value.push('\\');
+                    let c = self.advance();
+                    if c == '\n' {
+                        self.line += 1;
+                    }
+                    value.push(c);
+                }
+                '\\' if !is_raw => value.extend(self.escape_sequence()),
+                c => value.push(c),
             }
-            self.advance();
         }
 
         if self.is_at_end() {
-            println!("Unterminated string.");
+            self.error("Unterminated string".to_string());
             return;
         }
 
         // Consume the closing "
         self.advance();
 
-        let value = self.source[self.start + 1..self.current - 1].to_string();
         tokens.push(Token::new(
             match is_fstring {
                 true => TokenType::FString,
@@ -282,33 +407,130 @@ impl Scanner {
             },
             self.source[self.start..self.current].to_string(),
             Some(Literal::String(value)),
-            self.line,
+            line,
         ));
     }
 
+    /// Replaces the escape sequences in the text of a string.
+    pub fn unescape(text: &str) -> Result<String, ScanError> {
+        let mut scanner = Scanner::new(text.to_string());
+        let mut value = String::new();
+        while !scanner.is_at_end() {
+            match scanner.advance() {
+                '\\' => value.extend(scanner.escape_sequence()),
+                c => value.push(c),
+            }
+        }
+        match scanner.errors.into_iter().next() {
+            Some(error) => Err(error),
+            None => Ok(value),
+        }
+    }
+
+    /// The character an escape sequence stands for, after its backslash. A backslash at
+    /// the end of a line continues the string on the next line without the line break
+    /// and the indentation.
+    fn escape_sequence(&mut self) -> Option<char> {
+        // The string is reported as unterminated when the source ends after the backslash
+        if self.is_at_end() {
+            return None;
+        }
+        let c = self.advance();
+        match c {
+            'n' => Some('\n'),
+            't' => Some('\t'),
+            'r' => Some('\r'),
+            '0' => Some('\0'),
+            '"' | '`' | '\\' => Some(c),
+            'u' => self.unicode_escape(),
+            '\n' => self.line_continuation(),
+            '\r' if self.match_char('\n') => self.line_continuation(),
+            _ => {
+                self.error(format!("Unknown escape sequence '\\{}'", c));
+                None
+            }
+        }
+    }
+
+    /// Skips the indentation after a backslash at the end of a line, whose line break
+    /// was consumed.
+    fn line_continuation(&mut self) -> Option<char> {
+        self.line += 1;
+        while matches!(self.peek(), ' ' | '\t' | '\r' | '\n') {
+            if self.advance() == '\n' {
+                self.line += 1;
+            }
+        }
+        None
+    }
+
+    /// A unicode escape like `\u{1F600}`, after its `u`.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.match_char('{') {
+            self.error("Expect '{' after '\\u'".to_string());
+            return None;
+        }
+        let start = self.current;
+        while self.peek().is_ascii_hexdigit() {
+            self.advance();
+        }
+        let digits = self.source[start..self.current].to_string();
+        if !self.match_char('}') {
+            self.error("Expect '}' to close a unicode escape".to_string());
+            return None;
+        }
+        let c = match digits.len() {
+            1..=6 => char::from_u32(u32::from_str_radix(&digits, 16).unwrap()),
+            _ => None,
+        };
+        if c.is_none() {
+            self.error(format!("Invalid unicode escape '\\u{{{}}}'", digits));
+        }
+        c
+    }
+
     fn number(&mut self, tokens: &mut Vec<Token>) {
         while self.peek().is_ascii_digit() {
             self.advance();
         }
 
         // Look for a fractional part
+        let mut is_float = false;
         if self.peek() == '.' && self.peek_next().is_ascii_digit() {
             // Consume the "."
             self.advance();
+            is_float = true;
 
             while self.peek().is_ascii_digit() {
                 self.advance();
             }
         }
+        let digits = self.source[self.start..self.current].to_string();
 
+        // Followed by the name of its type, like `1i64`
+        while self.peek().is_alphanumeric() || self.peek() == '_' {
+            self.advance();
+        }
+        let suffix = &self.source[self.start + digits.len()..self.current];
+        let number_type = match NumberType::from_name(suffix) {
+            _ if suffix.is_empty() => None,
+            Some(NumberType::Float) => Some(NumberType::Float),
+            Some(number_type) if !is_float => Some(number_type),
+            Some(_) => return self.error(format!("A float can't have the suffix '{}'", suffix)),
+            None => return self.error(format!("Invalid number suffix '{}'", suffix)),
+        };
+
+        let value = match (is_float, number_type) {
+            (true, _) | (false, Some(NumberType::Float)) => Literal::Float(digits.parse().unwrap()),
+            (false, _) => match digits.parse() {
+                Ok(value) => Literal::Integer(value, number_type),
+                Err(_) => return self.error(format!("Integer literal '{}' is too large", digits)),
+            },
+        };
         tokens.push(Token::new(
             TokenType::Number,
             self.source[self.start..self.current].to_string(),
-            Some(Literal::Number(
-                self.source[self.start..self.current]
-                    .parse::<f64>()
-                    .unwrap(),
-            )),
+            Some(value),
             self.line,
         ));
     }
@@ -328,9 +550,15 @@ impl Scanner {
             "cmpnt" => TokenType::Cmpnt,
             "if" => TokenType::If,
             "else" => TokenType::Else,
+            "assert" => TokenType::Assert,
             "assert_eq" => TokenType::AssertEq,
+            "assert_ne" => TokenType::AssertNe,
+            "panic" => TokenType::Panic,
             "import" => TokenType::Import,
             "struct" => TokenType::Struct,
+            "loader" => TokenType::Loader,
+            "true" => TokenType::True,
+            "false" => TokenType::False,
 
             // Builtins.
             "__RUN_TEST" => TokenType::RunTest,
@@ -358,7 +586,7 @@ impl Scanner {
         match text.as_str() {
             "@js" => tokens.push(Token::new(TokenType::RawJs, text.clone(), None, self.line)),
             "@go" => tokens.push(Token::new(TokenType::RawGo, text.clone(), None, self.line)),
-            _ => panic!("Unexpected raw code: {}", text),
+            _ => self.error(format!("Unexpected raw code: {}", text)),
         }
     }
 }
@@ -1,5 +1,5 @@
 use crate::expression::Expression;
-use crate::token::Token;
+use crate::token::{NumberType, Token};
 
 #[derive(Debug)]
 pub enum MaybeStatement {
@@ -14,6 +14,26 @@ pub enum Declaration {
     Tuple(Vec<Token>),
 }
 
+/// An asserted expression, along with its fsf source so that a failed assertion can
+/// quote it.
+#[derive(Debug, Clone)]
+pub struct Asserted {
+    pub expression: Box<Expression>,
+    pub source: String,
+}
+
+/// How the test runner runs a test, from the test's attributes and its module.
+#[derive(Debug, Clone, Default)]
+pub struct TestOptions {
+    /// Run before the test and after it, even if it fails.
+    pub setup: Option<Box<Expression>>,
+    pub teardown: Option<Box<Expression>>,
+    /// Set for `#[ignore]`, to the reason if one is given.
+    pub ignore: Option<String>,
+    /// Set for `#[should_panic]`, to the text the panic message must contain if given.
+    pub should_panic: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub enum Statement {
     Print(Expression),
@@ -21,11 +41,32 @@ pub enum Statement {
     Let {
         declaration: Declaration,
         expression: Expression,
+        #[allow(dead_code)]
         mutable: bool,
+        /// The type of the variable when it's a number, filled in by the
+        /// `NumberTransformer`.
+        number_type: Option<NumberType>,
+    },
+    /// `assert(condition)`. The message of an assertion is any string expression,
+    /// usually an f-string, added to the failure message.
+    Assert {
+        condition: Asserted,
+        message: Option<Expression>,
+    },
+    AssertEq {
+        left: Asserted,
+        right: Asserted,
+        message: Option<Expression>,
+    },
+    AssertNe {
+        left: Asserted,
+        right: Asserted,
+        message: Option<Expression>,
     },
-    AssertEq(Expression, Expression),
+    Panic(Expression),
     RunTest {
         test_name: Token,
         function_name: Box<Expression>,
+        options: TestOptions,
     },
 }
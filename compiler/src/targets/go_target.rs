@@ -1,60 +1,156 @@
-use crate::expression::{Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk};
-use crate::item::Item;
+use crate::expression::{
+    Align, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk, FormatSpec,
+};
+use crate::item::{Item, Parameter};
 use crate::statement::{Declaration, Statement};
-use crate::targets::Program;
-use crate::token::{Literal, TokenType};
+use crate::targets::source_map::{line_marker, SourceMap, SourceMaps};
+use crate::targets::{string_literal, Program};
+use crate::token::{Literal, NumberType, Token, TokenType};
 use anyhow::Result;
 use itertools::Itertools;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// The parameter components, loaders and hooks take the params of the request being
+/// rendered by, which `use_params` returns. Elsewhere `fsfParams` is an empty map.
+const ROUTE_PARAMS: &str = "fsfParams map[string]string";
+
+/// Support code the generated Go depends on, written alongside every program.
+const BOOTSTRAP: [(&str, &str); 4] = [
+    (
+        "fsf_html_builder.go",
+        include_str!("../bootstrap/go_html_builder.go"),
+    ),
+    ("fsf_router.go", include_str!("../bootstrap/go_router.go")),
+    (
+        "fsf_server_actions.go",
+        include_str!("../bootstrap/go_server_actions.go"),
+    ),
+    ("fsf_testing.go", include_str!("../bootstrap/go_testing.go")),
+];
+
+/// The runner of the tests of a program with a `TestRunner` item, which reads its
+/// options from the environment.
+const TEST_RUNNER: (&str, &str) = (
+    "fsf_test_runner.go",
+    include_str!("../bootstrap/go_test_runner.go"),
+);
 
 pub struct GoTarget {
     building_html: bool,
+    components: HashMap<String, Vec<Parameter>>,
+    root: PathBuf,
+    /// The packages the code generated for the current module uses.
+    imports: BTreeSet<&'static str>,
 }
 
 impl GoTarget {
-    pub fn new() -> Self {
+    pub fn new(root: PathBuf) -> Self {
         Self {
             building_html: false,
+            components: HashMap::new(),
+            root,
+            imports: BTreeSet::new(),
         }
     }
 
-    pub fn compile(&mut self, program: Program, compile_dir: &Path) -> Result<()> {
+    /// Imports a package into the module being compiled.
+    fn import(&mut self, package: &'static str) {
+        self.imports.insert(package);
+    }
+
+    pub fn compile(&mut self, program: Program, compile_dir: &Path) -> Result<SourceMaps> {
+        // Components are called with positional arguments so we need their parameters
+        // to order the attributes of component elements
+        self.components = program
+            .iter()
+            .flat_map(|module| module.items.iter())
+            .filter_map(|item| match item {
+                Item::Component {
+                    name, parameters, ..
+                } => Some((name.clone(), parameters.clone())),
+                _ => None,
+            })
+            .collect();
+
+        let has_tests = program
+            .iter()
+            .flat_map(|module| module.items.iter())
+            .any(|item| matches!(item, Item::TestRunner));
+
+        let mut source_maps = SourceMaps::new();
         for module in program {
             let output = module
                 .items
                 .into_iter()
                 .map(|item| self.compile_item(item))
                 .join("");
-            let mut output_path = compile_dir.join(module.path.file_stem().unwrap());
-            output_path.set_extension("go");
-            let mut output_file = File::create(&output_path)?;
-            output_file.write_all("package main\n".as_bytes())?;
-            // TODO: Propagate this information up via the parser
-            if output.contains("fmt.") {
-                output_file.write_all("import \"fmt\"\n".as_bytes())?;
+            // Go packages are a single directory, so modules in subdirectories are named
+            // after their path, like `chirps.[id].go`
+            let module_name = match module.path.strip_prefix(&self.root) {
+                Ok(relative) => relative
+                    .with_extension("")
+                    .iter()
+                    .map(|p| p.to_string_lossy())
+                    .join("."),
+                Err(_) => module
+                    .path
+                    .file_stem()
+                    .unwrap()
+                    .to_string_lossy()
+                    .to_string(),
+            };
+            let output_path = compile_dir.join(format!("{}.go", module_name));
+
+            let mut file_contents = "package main\n".to_string();
+            for package in std::mem::take(&mut self.imports) {
+                file_contents.push_str(&format!("import \"{}\"\n", package));
             }
-            output_file.write_all(output.as_bytes())?;
+            file_contents.push_str(&output);
+
+            let (file_contents, source_map) =
+                SourceMap::extract_with_line_directives(module.source.as_deref(), &file_contents);
+            let mut output_file = File::create(&output_path)?;
+            output_file.write_all(file_contents.as_bytes())?;
+            source_maps.insert(output_path.canonicalize()?, source_map);
+        }
+
+        for (file_name, contents) in BOOTSTRAP
+            .into_iter()
+            .chain(has_tests.then_some(TEST_RUNNER))
+        {
+            let mut bootstrap_file = File::create(compile_dir.join(file_name))?;
+            bootstrap_file.write_all(contents.as_bytes())?;
         }
 
-        Ok(())
+        Ok(source_maps)
     }
 
     fn compile_item(&mut self, item: Item) -> String {
         match item {
             Item::Function {
                 name,
+                type_parameters,
                 parameters,
                 body,
                 return_type,
+                ..
             } => {
+                let type_params = match type_parameters.is_empty() {
+                    true => "".to_string(),
+                    false => format!(
+                        "[{}]",
+                        type_parameters
+                            .iter()
+                            .map(|t| format!("{} any", t))
+                            .join(", ")
+                    ),
+                };
                 let return_type = match return_type.as_deref() {
-                    Some("int") => "int",
-                    Some("str") => "string",
-                    Some("void") => "",
-                    Some(other) => other,
-                    None => "",
+                    Some("void") | None => "",
+                    other => map_type_annotation(other),
                 };
 
                 let statements = body
@@ -62,32 +158,35 @@ impl GoTarget {
                     .into_iter()
                     .map(|s| self.compile_statement(s))
                     .join("");
-                let params = parameters
-                    .iter()
-                    .map(|p| {
+                // Hooks are passed the params of the request being rendered
+                let params = is_hook(&name)
+                    .then(|| ROUTE_PARAMS.to_string())
+                    .into_iter()
+                    .chain(parameters.iter().map(|p| {
                         format!(
                             "{} {}",
                             p.name,
-                            match p.type_annotation.as_str() {
-                                "int" => "int",
-                                "str" => "string",
-                                other => other,
-                            }
+                            map_type_annotation(Some(&p.type_annotation))
                         )
-                    })
+                    }))
                     .join(", ");
+                let marker = line_marker(body.line);
                 match body.expr {
                     Some(expr) => format!(
-                        "func {}({}) {} {{\n{}\nreturn {}\n}}\n",
+                        "{}func {}{}({}) {} {{\n{}\nreturn {}\n}}\n",
+                        marker,
                         name,
+                        type_params,
                         params,
                         return_type,
                         statements,
                         self.compile_expression(expr)
                     ),
                     None => format!(
-                        "func {}({}) {} {{\n{}\n}}\n",
+                        "{}func {}{}({}) {} {{\n{}\n}}\n",
+                        marker,
                         name,
+                        type_params,
                         params,
                         // This is a HACK, because we have "string" types and injecting raw code.
                         // We need to be able to conditionally compile JS or Go to ensure typing
@@ -97,44 +196,91 @@ impl GoTarget {
                     ),
                 }
             }
+            Item::Action {
+                name,
+                parameters,
+                body,
+                return_type,
+                ..
+            } => {
+                let arity = parameters.len();
+                let decode_arguments = parameters
+                    .iter()
+                    .enumerate()
+                    .map(|(i, p)| {
+                        format!(
+                            "var {} {}\nif err := decodeArgument(arguments[{}], &{}); err != nil {{\nreturn nil, err\n}}\n",
+                            p.name,
+                            map_type_annotation(Some(p.type_annotation.as_str())),
+                            i,
+                            p.name
+                        )
+                    })
+                    .join("");
+                let call = format!(
+                    "{}({})",
+                    name,
+                    parameters.iter().map(|p| p.name.as_str()).join(", ")
+                );
+                let call = match return_type.as_deref() {
+                    None | Some("void") => format!("{}\nreturn nil, nil\n", call),
+                    Some(_) => format!("return {}, nil\n", call),
+                };
+                self.import("encoding/json");
+                self.import("fmt");
+                let registration = format!(
+                    "func init() {{\nregisterServerAction(\"{}\", func(arguments []json.RawMessage) (any, error) {{\nif len(arguments) != {} {{\nreturn nil, fmt.Errorf(\"{} expects {} arguments, got %d\", len(arguments))\n}}\n{}{}}})\n}}\n",
+                    name, arity, name, arity, decode_arguments, call
+                );
+                let function = self.compile_item(Item::Function {
+                    name,
+                    type_parameters: vec![],
+                    parameters,
+                    body,
+                    return_type,
+                    attributes: vec![],
+                    doc: None,
+                });
+                format!("{}{}", function, registration)
+            }
             Item::Component {
                 name,
                 parameters,
                 body,
+                ..
             } => {
                 let statements = body
                     .statements
                     .into_iter()
                     .map(|s| self.compile_statement(s))
                     .join("");
-                let params = parameters
-                    .iter()
-                    .map(|p| {
-                        format!(
-                            "{} {}",
-                            p.name,
-                            match p.type_annotation.as_str() {
-                                "int" => "int",
-                                "str" => "string",
-                                other => other,
-                            }
-                        )
-                    })
+                let params = [ROUTE_PARAMS.to_string()]
+                    .into_iter()
+                    .chain(
+                        parameters
+                            .iter()
+                            .map(|p| format!("{} {}", p.name, component_parameter_type(p))),
+                    )
                     .join(", ");
+                let marker = line_marker(body.line);
                 match body.expr {
                     Some(expr) => format!(
-                        "func {}({}) string {{\n{}\nreturn {}\n}}\n",
+                        "{}func {}({}) string {{\n{}\nreturn {}\n}}\n",
+                        marker,
                         name,
                         params,
                         statements,
                         self.compile_expression(expr)
                     ),
-                    None => format!("func {}({}) {{\n{}\n}}\n", name, params, statements),
+                    None => format!(
+                        "{}func {}({}) {{\n{}\n}}\n",
+                        marker, name, params, statements
+                    ),
                 }
             }
-            Item::Struct { name, fields } => {
+            Item::Struct { name, fields, .. } => {
                 let field_strings = fields
-                    .into_iter()
+                    .iter()
                     .map(|f| {
                         format!(
                             "{} {}",
@@ -143,41 +289,91 @@ impl GoTarget {
                         )
                     })
                     .join("\n");
-                format!("type {} struct {{\n{}\n}}\n", name, field_strings)
+                // Struct fields are unexported in Go so encoding/json needs to be told about
+                // them explicitly for loader data to reach the client
+                self.import("encoding/json");
+                let json_fields = fields
+                    .iter()
+                    .map(|f| format!("\"{}\": s.{},\n", f.name, f.name))
+                    .join("");
+                format!(
+                    "type {} struct {{\n{}\n}}\nfunc (s {}) MarshalJSON() ([]byte, error) {{\nreturn json.Marshal(map[string]any{{\n{}}})\n}}\n",
+                    name, field_strings, name, json_fields
+                )
+            }
+            Item::Loader {
+                name,
+                body,
+                return_type,
+                ..
+            } => self.compile_item(Item::Function {
+                name: format!("{}_loader", name),
+                type_parameters: vec![],
+                parameters: vec![Parameter {
+                    name: "fsfParams".to_string(),
+                    type_annotation: "map[string]string".to_string(),
+                }],
+                body,
+                return_type: Some(return_type),
+                attributes: vec![],
+                doc: None,
+            }),
+            Item::Router { routes } => {
+                let routes = routes
+                    .into_iter()
+                    .map(|route| {
+                        let (load, render) = match route.loader_type {
+                            Some(loader_type) => (
+                                format!("return {}_loader(params)", route.component),
+                                format!(
+                                    "return {}(params, data.({}))",
+                                    route.component,
+                                    map_type_annotation(Some(&loader_type))
+                                ),
+                            ),
+                            None => ("return nil".to_string(), format!("return {}(params)", route.component)),
+                        };
+                        format!(
+                            "\"{}\": {{\ncomponent: \"{}\",\nentry: \"{}\",\nload: func(params map[string]string) any {{\n{}\n}},\nrender: func(params map[string]string, data any) string {{\n{}\n}},\n}},\n",
+                            route.path, route.component, route.module, load, render
+                        )
+                    })
+                    .join("");
+                format!("var routes = map[string]fsfRoute{{\n{}}}\n", routes)
             }
             Item::Import { .. } => "".to_string(),
-            Item::TestRunner => r#"
-                func runTest(test func(), name string) {
-                    defer func() {
-                        if err := recover(); err != nil {
-                            fmt.Print(" fail\n")
-                        }
-                    }()
-
-                    fmt.Printf("%s...", name)
-                    test()
-                    fmt.Print(" pass\n")
-                }
-                "#
-            .to_string(),
+            // The runner is written to a file of its own, see `TEST_RUNNER`
+            Item::TestRunner => "".to_string(),
         }
     }
 
-    fn compile_statement(&mut self, statement: Statement) -> String {
-        match statement {
-            Statement::Print(expr) => format!("fmt.Println({})\n", self.compile_expression(expr)),
+    fn compile_statement(&mut self, (line, statement): (usize, Statement)) -> String {
+        let statement = match statement {
+            Statement::Print(expr) => {
+                self.import("fmt");
+                format!("fmt.Println({})\n", self.compile_expression(expr))
+            }
             Statement::Expression(expr) => format!("{}\n", self.compile_expression(expr)),
             Statement::Let {
                 declaration,
                 expression,
+                number_type,
                 ..
             } => {
                 // TODO: Create a unique temporary variable name generator
                 let tmp_var = "x_tmp";
-                let declaration_str = match declaration {
-                    Declaration::Name(ref name) => format!("{} := ", name.clone().value.unwrap()),
-                    Declaration::Array(_) => format!("{} := ", tmp_var),
-                    Declaration::Tuple(ref names) => {
+                let declaration_str = match (&declaration, number_type) {
+                    // Go would give an untyped constant the type `int` or `float64`
+                    (Declaration::Name(name), Some(number_type)) => format!(
+                        "var {} {} = ",
+                        name.clone().value.unwrap(),
+                        map_number_type(number_type)
+                    ),
+                    (Declaration::Name(name), None) => {
+                        format!("{} := ", name.clone().value.unwrap())
+                    }
+                    (Declaration::Array(_), _) => format!("{} := ", tmp_var),
+                    (Declaration::Tuple(names), _) => {
                         format!(
                             "{} := ",
                             names.iter().cloned().map(|t| t.value.unwrap()).join(", ")
@@ -238,30 +434,96 @@ impl GoTarget {
                     },
                 }
             }
-            Statement::AssertEq(left, right) => {
+            Statement::Panic(message) => format!("panic({})\n", self.compile_expression(message)),
+            Statement::Assert { condition, message } => {
+                let failure =
+                    self.assertion_failure(format!("assert({})", condition.source), message);
+                format!(
+                    "if !({}) {{\npanic({})\n}}\n",
+                    self.compile_expression(*condition.expression),
+                    failure
+                )
+            }
+            // Both values are passed to a generic helper, which infers one type for them so
+            // that an untyped constant takes the type of the other side
+            Statement::AssertEq {
+                left,
+                right,
+                message,
+            } => {
+                let failure = self.assertion_failure(
+                    format!("assert_eq({}, {})", left.source, right.source),
+                    message,
+                );
+                format!(
+                    "assertEq({}, {}, {})\n",
+                    failure,
+                    self.compile_expression(*left.expression),
+                    self.compile_expression(*right.expression),
+                )
+            }
+            Statement::AssertNe {
+                left,
+                right,
+                message,
+            } => {
+                let failure = self.assertion_failure(
+                    format!("assert_ne({}, {})", left.source, right.source),
+                    message,
+                );
                 format!(
-                    "if ({} != {}) {{\npanic(`{} != {}`)}}\n",
-                    self.compile_expression(left.clone()),
-                    self.compile_expression(right.clone()),
-                    // TODO: Replace with source not compiled form
-                    self.compile_expression(left),
-                    self.compile_expression(right),
+                    "assertNe({}, {}, {})\n",
+                    failure,
+                    self.compile_expression(*left.expression),
+                    self.compile_expression(*right.expression),
                 )
             }
             Statement::RunTest {
                 test_name,
                 function_name,
+                options,
             } => {
                 if let Some(Literal::String(test_name)) = test_name.value {
+                    let options = [
+                        options
+                            .setup
+                            .map(|setup| format!("setup: {}", self.compile_expression(*setup))),
+                        options.teardown.map(|teardown| {
+                            format!("teardown: {}", self.compile_expression(*teardown))
+                        }),
+                        options.ignore.map(|reason| {
+                            format!("ignored: true, ignoreReason: {}", string_literal(&reason))
+                        }),
+                        options.should_panic.map(|expected| {
+                            format!(
+                                "shouldPanic: true, expectedPanic: {}",
+                                string_literal(&expected)
+                            )
+                        }),
+                    ];
                     format!(
-                        "runTest({}, \"{}\")\n",
+                        "runTest({}, {}, testOptions{{{}}})\n",
                         self.compile_expression(*function_name),
-                        test_name
+                        string_literal(&test_name),
+                        options.into_iter().flatten().join(", ")
                     )
                 } else {
                     panic!("Test name must be a string");
                 }
             }
+        };
+        format!("{}{}", line_marker(line), statement)
+    }
+
+    /// The string a failed `assertion` panics with, followed by its message if it has one.
+    fn assertion_failure(&mut self, assertion: String, message: Option<Expression>) -> String {
+        match message {
+            Some(message) => format!(
+                "{} + {}",
+                string_literal(&format!("{} failed: ", assertion)),
+                self.compile_expression(message)
+            ),
+            None => string_literal(&format!("{} failed", assertion)),
         }
     }
 
@@ -310,38 +572,66 @@ impl GoTarget {
                 left,
                 operator,
                 right,
+                ..
             } => format!(
                 "{} {} {}",
                 self.compile_expression(*left),
                 operator.lexeme,
                 self.compile_expression(*right)
             ),
-            ExpressionWithoutBlock::Call { callee, arguments } => format!(
-                "{}({})",
-                self.compile_expression(*callee),
-                arguments
-                    .into_iter()
-                    .map(|e| self.compile_expression(e))
-                    .join(", ")
-            ),
+            ExpressionWithoutBlock::Call { callee, arguments } => {
+                let params = match callee.as_ref() {
+                    ExpressionWithoutBlock::Variable(name) if is_hook(&name.lexeme) => {
+                        Some("fsfParams".to_string())
+                    }
+                    _ => None,
+                };
+                format!(
+                    "{}({})",
+                    self.compile_expression(*callee),
+                    params
+                        .into_iter()
+                        .chain(arguments.into_iter().map(|e| self.compile_expression(e)))
+                        .join(", ")
+                )
+            }
             ExpressionWithoutBlock::Grouping(expression) => {
                 format!("({})", self.compile_expression(*expression))
             }
-            ExpressionWithoutBlock::Literal(Literal::Number(number)) => format!("{}", number),
-            ExpressionWithoutBlock::Literal(Literal::String(string)) => format!("\"{}\"", string),
+            ExpressionWithoutBlock::Literal(Literal::Integer(integer, Some(NumberType::Float))) => {
+                format!("{}.0", integer)
+            }
+            ExpressionWithoutBlock::Literal(Literal::Integer(integer, _)) => integer.to_string(),
+            ExpressionWithoutBlock::Literal(Literal::Float(float)) => format!("{:?}", float),
+            ExpressionWithoutBlock::Literal(Literal::String(string)) => string_literal(&string),
             ExpressionWithoutBlock::Literal(Literal::Identifier(identifier)) => {
                 identifier.to_string()
             }
             ExpressionWithoutBlock::Literal(Literal::True) => "true".to_string(),
             ExpressionWithoutBlock::Literal(Literal::False) => "false".to_string(),
-            ExpressionWithoutBlock::Unary { .. } => todo!(),
+            ExpressionWithoutBlock::Unary {
+                operator, right, ..
+            } => {
+                let right = self.compile_expression_without_block(*right);
+                // `--x` would be a decrement
+                match right.starts_with('-') {
+                    true => format!("{}({})", operator.lexeme, right),
+                    false => format!("{}{}", operator.lexeme, right),
+                }
+            }
             ExpressionWithoutBlock::Variable(identifier) => {
                 format!("{}", identifier.value.unwrap())
             }
+            ExpressionWithoutBlock::Convert { expression, to, .. } => format!(
+                "{}({})",
+                map_number_type(to),
+                self.compile_expression(*expression)
+            ),
             ExpressionWithoutBlock::Assignment {
                 name,
                 value,
                 operator,
+                ..
             } => match operator.token_type {
                 TokenType::Equal => {
                     format!("{} = {}", name.lexeme, self.compile_expression(*value))
@@ -352,21 +642,34 @@ impl GoTarget {
                 _ => panic!("Unexpected token type in assignment: {}", operator.lexeme),
             },
             ExpressionWithoutBlock::FString { chunks } => {
-                let format_string = chunks
-                    .iter()
-                    .map(|chunk| match chunk {
-                        FStringChunk::Literal(string) => string,
-                        FStringChunk::Identifier(_string) => "%v", // TODO: Use correct specifier based on type
-                    })
-                    .join("");
-                let arguments = chunks
-                    .iter()
-                    .filter_map(|chunk| match chunk {
-                        FStringChunk::Literal(_) => None,
-                        FStringChunk::Identifier(string) => Some(string),
-                    })
-                    .join(", ");
-                format!("fmt.Sprintf(\"{}\", {})", format_string, arguments)
+                let mut format_string = String::new();
+                let mut arguments = Vec::new();
+                for chunk in chunks {
+                    match chunk {
+                        FStringChunk::Literal(string) => {
+                            format_string.push_str(&string.replace('%', "%%"))
+                        }
+                        FStringChunk::Expression {
+                            expression,
+                            format,
+                            number_type,
+                        } => {
+                            format_string.push_str(&format_verb(&format, number_type));
+                            arguments.push(self.compile_expression(*expression));
+                        }
+                    }
+                }
+                match arguments.is_empty() {
+                    true => string_literal(&format_string.replace("%%", "%")),
+                    false => {
+                        self.import("fmt");
+                        format!(
+                            "fmt.Sprintf({}, {})",
+                            string_literal(&format_string),
+                            arguments.join(", ")
+                        )
+                    }
+                }
             }
             ExpressionWithoutBlock::Html {
                 name,
@@ -385,30 +688,22 @@ impl GoTarget {
                     true
                 };
 
-                output.push_str(&format!("builder.beginElement(\"{}\")\n", name.lexeme));
-                for (name, value_expr) in attributes {
-                    output.push_str(&format!(
-                        "builder.addAttribute(\"{}\", {})\n",
-                        name.lexeme,
-                        self.compile_expression(value_expr).replace("\n", ""),
-                    ));
-                }
-                for expression in inner {
-                    match expression {
-                        Expression::WithoutBlock(ExpressionWithoutBlock::FString { .. })
-                        | Expression::WithoutBlock(ExpressionWithoutBlock::Literal(
-                            Literal::String(_),
-                        )) => {
-                            let compiled_expression = self.compile_expression(expression);
-                            output
-                                .push_str(&format!("builder.addString({})\n", compiled_expression));
-                        }
-                        _ => {
-                            output.push_str(&self.compile_expression(expression));
-                        }
+                if name.lexeme.starts_with(char::is_uppercase) {
+                    output.push_str(&self.compile_component_element(name, inner, attributes));
+                } else {
+                    output.push_str(&format!("builder.beginElement(\"{}\")\n", name.lexeme));
+                    for (name, value_expr) in attributes {
+                        output.push_str(&format!(
+                            "builder.addAttribute(\"{}\", {})\n",
+                            name.lexeme,
+                            self.compile_expression(value_expr).replace("\n", ""),
+                        ));
+                    }
+                    for expression in inner {
+                        output.push_str(&self.compile_html_child(expression));
                     }
+                    output.push_str("builder.endElement()\n");
                 }
-                output.push_str("builder.endElement()\n");
 
                 if require_cleanup {
                     output.push_str("return builder.build()\n}()");
@@ -432,7 +727,7 @@ impl GoTarget {
                     Expression::WithoutBlock(expression) => {
                         // TODO: Add proper type inference
                         format!(
-                            "func({}) int {{\nreturn {}\n}}\n",
+                            "func({}) int32 {{\nreturn {}\n}}\n",
                             params,
                             self.compile_expression_without_block(expression)
                         )
@@ -440,10 +735,13 @@ impl GoTarget {
                     Expression::WithBlock(_expression) => todo!(),
                 }
             }
-            ExpressionWithoutBlock::Array { elements } => {
+            ExpressionWithoutBlock::Array {
+                elements,
+                element_type,
+            } => {
                 let count = elements.len();
-                // TODO: Add proper type information
-                let type_ = "int";
+                // TODO: Add type information for arrays of other than numbers
+                let type_ = map_number_type(element_type.unwrap_or(NumberType::Int));
                 let elements = elements
                     .into_iter()
                     .map(|e| self.compile_expression(e))
@@ -484,13 +782,141 @@ impl GoTarget {
             ExpressionWithoutBlock::RawGo(code) => format!("{}\n", code),
         }
     }
+
+    /// Renders a component element by calling the component with its attributes in
+    /// parameter order. The element's children are passed as the `children` parameter.
+    fn compile_component_element(
+        &mut self,
+        name: Token,
+        inner: Vec<Expression>,
+        attributes: Vec<(Token, Expression)>,
+    ) -> String {
+        let parameters = self
+            .components
+            .get(&name.lexeme)
+            .cloned()
+            .unwrap_or_else(|| panic!("Unknown component: {}", name.lexeme));
+        let mut attributes: HashMap<String, Expression> = attributes
+            .into_iter()
+            .map(|(name, value)| (name.lexeme, value))
+            .collect();
+
+        let mut arguments = vec!["fsfParams".to_string()];
+        let mut inner = Some(inner);
+        for parameter in parameters {
+            let argument = match attributes.remove(&parameter.name) {
+                Some(value) if parameter.name == "children" => {
+                    format!(
+                        "toHTML({})",
+                        self.compile_expression(value).replace("\n", "")
+                    )
+                }
+                Some(value) => self.compile_expression(value).replace("\n", ""),
+                None if parameter.name == "children" => {
+                    self.compile_html_children(inner.take().unwrap_or_default())
+                }
+                None => format!("*new({})", component_parameter_type(&parameter)),
+            };
+            arguments.push(argument);
+        }
+
+        format!(
+            "builder.addString({}({}))\n",
+            name.lexeme,
+            arguments.join(", ")
+        )
+    }
+
+    /// Renders children into their own builder, producing HTML that isn't escaped again
+    /// when the component adds it to its own.
+    fn compile_html_children(&mut self, inner: Vec<Expression>) -> String {
+        let building_html = self.building_html;
+        self.building_html = true;
+
+        let mut output = "func() HTML {\nbuilder := NewHTMLBuilder()\n".to_string();
+        for expression in inner {
+            output.push_str(&self.compile_html_child(expression));
+        }
+        output.push_str("return HTML(builder.build())\n}()");
+
+        self.building_html = building_html;
+        output
+    }
+
+    /// Compiles a child of an element. The literal text of the template is added as it
+    /// is written, while interpolated values are escaped unless they are `children`.
+    fn compile_html_child(&mut self, expression: Expression) -> String {
+        match expression {
+            Expression::WithoutBlock(ExpressionWithoutBlock::Literal(Literal::String(_))) => {
+                format!(
+                    "builder.addString({})\n",
+                    self.compile_expression(expression)
+                )
+            }
+            Expression::WithoutBlock(
+                ExpressionWithoutBlock::FString { .. }
+                | ExpressionWithoutBlock::Variable(_)
+                | ExpressionWithoutBlock::Field { .. }
+                | ExpressionWithoutBlock::Index { .. }
+                | ExpressionWithoutBlock::Call { .. },
+            ) => format!(
+                "builder.addChild({})\n",
+                self.compile_expression(expression)
+            ),
+            _ => self.compile_expression(expression),
+        }
+    }
+}
+
+/// Whether a function is a hook, like `use_params`, whose name starts with `use_`.
+fn is_hook(name: &str) -> bool {
+    name.starts_with("use_")
+}
+
+/// The verb that formats an f-string expression, like `%8.2f` for `{x:>8.2}`.
+fn format_verb(format: &FormatSpec, number_type: Option<NumberType>) -> String {
+    let align = format.align.unwrap_or(match number_type {
+        Some(_) => Align::Right,
+        None => Align::Left,
+    });
+    // Only numbers are padded with zeros
+    let (flag, width) = match (format.width, format.zero && number_type.is_some(), align) {
+        (None, _, _) => ("", String::new()),
+        (Some(width), true, _) => ("0", width.to_string()),
+        (Some(width), false, Align::Left) => ("-", width.to_string()),
+        (Some(width), false, Align::Right) => ("", width.to_string()),
+    };
+    let (precision, verb) = match (number_type, format.precision) {
+        (Some(NumberType::Float), Some(precision)) => (format!(".{}", precision), "f"),
+        (Some(NumberType::Int | NumberType::I64), _) => (String::new(), "d"),
+        _ => (String::new(), "v"),
+    };
+    format!("%{}{}{}{}", flag, width, precision, verb)
+}
+
+/// The Go type of a component's parameter. Children are rendered HTML rather than text.
+fn component_parameter_type(parameter: &Parameter) -> &str {
+    match parameter.name.as_str() {
+        "children" => "HTML",
+        _ => map_type_annotation(Some(&parameter.type_annotation)),
+    }
 }
 
 fn map_type_annotation(type_: Option<&str>) -> &str {
     match type_ {
-        Some("int") => "int",
         Some("str") => "string",
-        Some(other) => other,
-        None => "int",
+        Some(other) => match NumberType::from_name(other) {
+            Some(number_type) => map_number_type(number_type),
+            None => other,
+        },
+        None => "int32",
+    }
+}
+
+fn map_number_type(number_type: NumberType) -> &'static str {
+    match number_type {
+        NumberType::Int => "int32",
+        NumberType::I64 => "int64",
+        NumberType::Float => "float64",
     }
 }
@@ -1,42 +1,102 @@
-use crate::expression::{Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk};
-use crate::item::Item;
+use crate::expression::{
+    Align, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk, FormatSpec,
+};
+use crate::item::{Item, Parameter};
 use crate::statement::Declaration;
 use crate::statement::Statement;
-use crate::targets::Program;
-use crate::token::{Literal, TokenType};
+use crate::targets::source_map::{line_marker, SourceMap, SourceMaps};
+use crate::targets::{escape, string_literal, Program};
+use crate::token::{Literal, NumberType, TokenType};
 use anyhow::Result;
 use itertools::Itertools;
-use std::collections::HashMap;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
 use std::fs::File;
 use std::io::Write;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 const MAIN_BOOTSTRAP: &str = include_str!("../bootstrap/js_bootstrap.js");
+const NODE_RUNTIME: &str = include_str!("../bootstrap/js_node_runtime.js");
+const NUMBERS: &str = include_str!("../bootstrap/js_numbers.js");
+const ASSERTIONS: &str = include_str!("../bootstrap/js_assertions.js");
+const TEST_RUNNER: &str = include_str!("../bootstrap/js_test_runner.js");
+/// The helpers that are written next to the compiled modules, with the functions each
+/// exports. Modules import the functions they call from them.
+const HELPER_MODULES: [(&str, &str, &[&str]); 2] = [
+    (
+        "fsf_numbers",
+        NUMBERS,
+        &[
+            "fsfDivideInt",
+            "fsfDivideI64",
+            "fsfToInt",
+            "fsfToI64",
+            "fsfToFloat",
+            "fsfToFixed",
+            "fsfPad",
+            "fsfPrint",
+            "fsfFormat",
+        ],
+    ),
+    ("fsf_assertions", ASSERTIONS, &["fsfEqual"]),
+];
 const REACT_BOOTSTRAP_HEADER: &str = include_str!("../bootstrap/react_bootstrap_header.js");
 const REACT_BOOTSTRAP_FOOTER: &str = include_str!("../bootstrap/react_bootstrap_footer.js");
 
-pub struct JsTarget {}
+pub struct JsTarget {
+    is_exec_mode: bool,
+    root: PathBuf,
+    /// The module being compiled, relative to the root and without an extension.
+    module: String,
+    /// The module that exports each function and component, by name.
+    exports: HashMap<String, String>,
+    /// The names the module being compiled refers to.
+    used: HashSet<String>,
+    /// The functions the module being compiled calls from each helper module.
+    helpers: BTreeMap<&'static str, BTreeSet<&'static str>>,
+}
 
 impl JsTarget {
-    pub fn new() -> Self {
-        Self {}
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            is_exec_mode: false,
+            root,
+            module: String::new(),
+            exports: HashMap::new(),
+            used: HashSet::new(),
+            helpers: BTreeMap::new(),
+        }
     }
 
+    /// In exec mode the program is written to a single `main.js` for node to run.
+    /// Otherwise every module becomes an ES module under `modules/`, with an entry
+    /// point per route under `entries/` for the bundler to split on.
     pub fn compile(
         &mut self,
         program: Program,
         compile_dir: &Path,
         is_exec_mode: bool,
-    ) -> Result<()> {
-        let output_path = match is_exec_mode {
-            true => compile_dir.join("main.js"),
-            false => compile_dir.join("index.jsx"),
-        };
-        let mut output_file = File::create(&output_path)?;
-        if !is_exec_mode {
-            output_file.write_all(REACT_BOOTSTRAP_HEADER.as_bytes())?;
+    ) -> Result<SourceMaps> {
+        self.is_exec_mode = is_exec_mode;
+        match is_exec_mode {
+            true => self.compile_executable(program, compile_dir),
+            false => self.compile_modules(program, compile_dir),
         }
+    }
 
+    fn compile_executable(&mut self, program: Program, compile_dir: &Path) -> Result<SourceMaps> {
+        let output_path = compile_dir.join("main.js");
+        let mut output_file = File::create(&output_path)?;
+
+        // Every module is written to the same file, so their source maps are offset by
+        // the lines written before them
+        output_file.write_all(NODE_RUNTIME.as_bytes())?;
+        output_file.write_all(NUMBERS.as_bytes())?;
+        output_file.write_all(ASSERTIONS.as_bytes())?;
+        let mut source_map = SourceMap::default();
+        let mut line_offset = [NODE_RUNTIME, NUMBERS, ASSERTIONS]
+            .iter()
+            .map(|bootstrap| bootstrap.matches('\n').count())
+            .sum::<usize>();
         for module in program {
             let output = module
                 .items
@@ -44,55 +104,206 @@ impl JsTarget {
                 .map(|item| self.compile_item(item))
                 .join("");
 
+            let (output, module_source_map) = SourceMap::extract(module.source.as_deref(), &output);
+            source_map.append(module_source_map, line_offset);
+            line_offset += output.matches('\n').count();
             output_file.write_all(output.as_bytes())?;
         }
 
-        if is_exec_mode {
-            output_file.write_all(MAIN_BOOTSTRAP.as_bytes())?;
-        } else {
-            // TODO: Write a component map so we know which component to hydrate the root
-            output_file.write_all(REACT_BOOTSTRAP_FOOTER.as_bytes())?;
-        }
-        Ok(())
+        output_file.write_all(MAIN_BOOTSTRAP.as_bytes())?;
+        write_source_map(&output_path, &mut output_file, &source_map)?;
+        Ok(SourceMaps::from([(
+            output_path.canonicalize()?,
+            source_map,
+        )]))
     }
 
-    fn construct_name_map(root: &Path, program: &Program) -> HashMap<String, String> {
-        program
-            .iter()
-            .flat_map(|module| {
-                module.items.iter().filter_map(|item| match item {
-                    // TODO: Add resolving relative imports (not from project root)
-                    Item::Import { path } => Some((path.last().unwrap().clone(), path.join("_"))),
-
-                    // TODO: Add transformer step to find main function and add bootstrapping
-                    Item::Function { name, .. } => {
-                        if name == "main" {
-                            Some((name.clone(), name.clone()))
-                        } else {
-                            Some((
-                                name.clone(),
-                                format!(
-                                    "{}_{}",
-                                    Path::new(
-                                        module
-                                            .path
-                                            .strip_prefix(root)
-                                            .unwrap()
-                                            .file_stem()
-                                            .unwrap()
-                                    )
-                                    .iter()
-                                    .map(|p| p.to_string_lossy())
-                                    .join("_"),
-                                    name
-                                ),
-                            ))
-                        }
+    fn compile_modules(&mut self, program: Program, compile_dir: &Path) -> Result<SourceMaps> {
+        let mut source_maps = SourceMaps::new();
+        let modules_dir = compile_dir.join("modules");
+        let entries_dir = compile_dir.join("entries");
+        std::fs::create_dir_all(&entries_dir)?;
+        std::fs::create_dir_all(&modules_dir)?;
+
+        for (helper_module, contents, functions) in HELPER_MODULES {
+            std::fs::write(
+                modules_dir.join(format!("{}.js", helper_module)),
+                format!("{}\nexport {{{}}};\n", contents, functions.join(", ")),
+            )?;
+        }
+
+        for module in &program {
+            let module_name = self.module_name(&module.path)?;
+            for item in &module.items {
+                if let Item::Function { name, .. }
+                | Item::Action { name, .. }
+                | Item::Component { name, .. } = item
+                {
+                    self.exports.insert(name.clone(), module_name.clone());
+                }
+            }
+        }
+
+        for module in program {
+            let module_name = self.module_name(&module.path)?;
+            self.module = module_name.clone();
+
+            for item in &module.items {
+                if let Item::Router { routes } = item {
+                    for route in routes {
+                        let entry_path = entries_dir.join(format!("{}.js", route.module));
+                        std::fs::create_dir_all(entry_path.parent().unwrap())?;
+                        let depth = route.module.matches('/').count() + 1;
+                        let modules_path = format!("{}modules", "../".repeat(depth));
+                        let mut entry_file = File::create(entry_path)?;
+                        entry_file.write_all(
+                            format!(
+                                "import \"{}/{}.js\";\nimport {{hydrate}} from \"{}/{}.js\";\n\nhydrate();\n",
+                                modules_path, route.module, modules_path, module_name
+                            )
+                            .as_bytes(),
+                        )?;
                     }
+                }
+            }
+
+            // Every module compiles into the same Go package, where items are visible
+            // without being imported, so the items a module uses from other modules are
+            // imported for it
+            let defined = module
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::Import { path } => path.last(),
+                    Item::Function { name, .. }
+                    | Item::Action { name, .. }
+                    | Item::Component { name, .. } => Some(name),
                     _ => None,
                 })
-            })
-            .collect()
+                .cloned()
+                .collect::<HashSet<_>>();
+            self.used.clear();
+            let output = module
+                .items
+                .into_iter()
+                .map(|item| self.compile_item(item))
+                .join("");
+            let helper_imports = std::mem::take(&mut self.helpers)
+                .into_iter()
+                .map(|(helper_module, functions)| match functions.is_empty() {
+                    true => format!("import \"{}\";\n", self.import_path(helper_module)),
+                    false => format!(
+                        "import {{{}}} from \"{}\";\n",
+                        functions.iter().join(", "),
+                        self.import_path(helper_module)
+                    ),
+                })
+                .join("");
+            let output = std::mem::take(&mut self.used)
+                .into_iter()
+                .filter(|name| !defined.contains(name))
+                .filter_map(|name| Some((self.exports.get(&name)?, name)))
+                .filter(|(module, _)| **module != module_name)
+                .sorted()
+                .map(|(module, name)| {
+                    format!(
+                        "import {{{}}} from \"{}\";\n",
+                        name,
+                        self.import_path(module)
+                    )
+                })
+                .join("")
+                + &helper_imports
+                + &output;
+
+            let output_path = modules_dir.join(format!("{}.js", module_name));
+            std::fs::create_dir_all(output_path.parent().unwrap())?;
+            let (output, source_map) = SourceMap::extract(
+                module.source.as_deref(),
+                &format!("{}{}", REACT_BOOTSTRAP_HEADER, output),
+            );
+            let mut output_file = File::create(&output_path)?;
+            output_file.write_all(output.as_bytes())?;
+            write_source_map(&output_path, &mut output_file, &source_map)?;
+            source_maps.insert(output_path.canonicalize()?, source_map);
+        }
+
+        Ok(source_maps)
+    }
+
+    /// The path of a module relative to the root, without an extension.
+    fn module_name(&self, path: &Path) -> Result<String> {
+        Ok(path
+            .strip_prefix(&self.root)?
+            .with_extension("")
+            .iter()
+            .map(|p| p.to_string_lossy())
+            .join("/"))
+    }
+
+    /// The path that imports `module`, relative to the root, from the module being
+    /// compiled.
+    fn import_path(&self, module: &str) -> String {
+        let depth = self.module.matches('/').count();
+        match depth {
+            0 => format!("./{}.js", module),
+            _ => format!("{}{}.js", "../".repeat(depth), module),
+        }
+    }
+
+    /// Calls a function of a helper module, which the module being compiled then imports
+    /// it from.
+    fn helper(&mut self, function: &'static str) -> &'static str {
+        let (helper_module, ..) = HELPER_MODULES
+            .iter()
+            .find(|(_, _, functions)| functions.contains(&function))
+            .unwrap();
+        self.helpers
+            .entry(helper_module)
+            .or_default()
+            .insert(function);
+        function
+    }
+
+    /// Formats an f-string expression the way Go formats it with the verb of its format
+    /// specifier.
+    fn format_value(
+        &mut self,
+        value: String,
+        format: &FormatSpec,
+        number_type: Option<NumberType>,
+    ) -> String {
+        let value = match (number_type, format.precision) {
+            (Some(NumberType::Float), Some(precision)) => {
+                format!("{}({}, {})", self.helper("fsfToFixed"), value, precision)
+            }
+            _ => value,
+        };
+        match format.width {
+            Some(width) => {
+                let align = format.align.unwrap_or(match number_type {
+                    Some(_) => Align::Right,
+                    None => Align::Left,
+                });
+                format!(
+                    "{}(String({}), {}, {}, {})",
+                    self.helper("fsfPad"),
+                    value,
+                    width,
+                    align == Align::Left,
+                    format.zero && number_type.is_some()
+                )
+            }
+            None => value,
+        }
+    }
+
+    /// Items are exported from their ES module so that other modules can import them.
+    fn export(&self) -> &'static str {
+        match self.is_exec_mode {
+            true => "",
+            false => "export ",
+        }
     }
 
     fn compile_item(&mut self, item: Item) -> String {
@@ -101,6 +312,7 @@ impl JsTarget {
                 name,
                 parameters,
                 body,
+                ..
             } => {
                 let statements = body
                     .statements
@@ -113,7 +325,9 @@ impl JsTarget {
                 // TODO: Typechecker will ensure we are returning HTML
                 let html_expr = body.expr.unwrap();
                 format!(
-                    "function {}({}) {{\n{}\n return ({});\n}}\n",
+                    "{}{}function {}({{{}}}) {{\n{}\n return ({});\n}}\n",
+                    line_marker(body.line),
+                    self.export(),
                     name,
                     params,
                     statements,
@@ -134,37 +348,207 @@ impl JsTarget {
 
                 let params = parameters.iter().map(|p| p.name.clone()).join(", ");
 
+                let marker = line_marker(body.line);
                 match body.expr {
                     Some(expr) => format!(
-                        "function {}({}) {{\n{}\nreturn {}\n}}\n",
+                        "{}{}function {}({}) {{\n{}\nreturn {}\n}}\n",
+                        marker,
+                        self.export(),
                         name,
                         params,
                         statements,
                         self.compile_expression(expr)
                     ),
-                    None => format!("function {}({}) {{\n{}\n}}\n", name, params, statements),
+                    None => format!(
+                        "{}{}function {}({}) {{\n{}\n}}\n",
+                        marker,
+                        self.export(),
+                        name,
+                        params,
+                        statements
+                    ),
                 }
             }
+            // Outside the browser there is no server to call so run the body directly
+            Item::Action {
+                name,
+                parameters,
+                body,
+                return_type,
+                ..
+            } if self.is_exec_mode => self.compile_item(Item::Function {
+                name,
+                type_parameters: vec![],
+                parameters,
+                body,
+                return_type,
+                attributes: vec![],
+                doc: None,
+            }),
+            Item::Action {
+                name, parameters, ..
+            } => {
+                let params = parameters.iter().map(|p| p.name.clone()).join(", ");
+                let form_values = parameters.iter().map(form_value).join(", ");
+                // i64 arguments are sent as strings by the `toJSON` fsf_numbers gives BigInts
+                self.helpers.entry("fsf_numbers").or_default();
+                format!(
+                    r#"export async function {name}({params}) {{
+                    let args = [{params}];
+                    // Used directly as a form event handler, read the arguments from the form
+                    const event = arguments[0];
+                    if (event && typeof event.preventDefault === "function") {{
+                        event.preventDefault();
+                        const form = new FormData(event.currentTarget);
+                        args = [{form_values}];
+                    }}
+                    const response = await fetch("/__actions/{name}", {{
+                        method: "POST",
+                        headers: {{"Content-Type": "application/json"}},
+                        body: JSON.stringify(args),
+                    }});
+                    if (!response.ok) {{
+                        throw new Error(`Server action {name} failed: ${{await response.text()}}`);
+                    }}
+                    return response.json();
+                }}
+                "#
+                )
+            }
             Item::Struct { .. } => "".to_string(),
+            // Imports are resolved relative to the importing module
+            // The standard library is imported from the root, other modules from the
+            // importing module's directory
+            Item::Import { path } if !self.is_exec_mode => {
+                let (name, segments) = path.split_last().unwrap();
+                let module = match segments.first().map(String::as_str) {
+                    Some("std") => segments.join("/"),
+                    _ => Path::new(&self.module)
+                        .parent()
+                        .unwrap_or(Path::new(""))
+                        .join(segments.join("/"))
+                        .to_string_lossy()
+                        .to_string(),
+                };
+                format!(
+                    "import {{{}}} from \"{}\";\n",
+                    name,
+                    self.import_path(&module)
+                )
+            }
             Item::Import { .. } => "".to_string(),
-            Item::TestRunner => r#"
-                function runTest(test, name) {
-                    process.stdout.write(`${name}...`);
-
-                    try {
-                        test();
-                        process.stdout.write(" pass\n");
-                    } catch (err) {
-                        process.stdout.write(" fail\n");
-                    }
-                }"#
-            .to_string(),
+            // Loaders only ever run on the server, the client fetches their results
+            Item::Loader { .. } => "".to_string(),
+            Item::Router { routes } => {
+                let routes = routes
+                    .into_iter()
+                    .map(|route| {
+                        // Route components are imported lazily so each page only loads its own
+                        format!(
+                            "\"{}\": {{load: () => import(\"./{}.js\").then((module) => module.{}), parameter: {}, hasLoader: {}}},\n",
+                            route.path,
+                            route.module,
+                            route.component,
+                            route
+                                .parameter
+                                .map(|p| format!("\"{}\"", p))
+                                .unwrap_or("undefined".to_string()),
+                            route.loader_type.is_some()
+                        )
+                    })
+                    .join("");
+                format!(
+                    r#"
+                const routes = {{
+                {routes}}};
+
+                const FsfRouteContext = React.createContext({{params: {{}}}});
+                let fsfRoot = null;
+
+                // Static routes take precedence over routes with dynamic `[name]` segments,
+                // which are listed in the order the server matches them
+                function matchRoute(path) {{
+                    if (routes[path]) {{
+                        return {{route: routes[path], params: {{}}}};
+                    }}
+                    const segments = path.replace(/^\/+|\/+$/g, "").split("/");
+                    for (const [pattern, route] of Object.entries(routes)) {{
+                        const patternSegments = pattern.replace(/^\/+|\/+$/g, "").split("/");
+                        if (patternSegments.length !== segments.length) {{
+                            continue;
+                        }}
+                        const params = {{}};
+                        const matched = patternSegments.every((segment, i) => {{
+                            if (segment.startsWith("[") && segment.endsWith("]")) {{
+                                params[segment.slice(1, -1)] = decodeURIComponent(segments[i]);
+                                return true;
+                            }}
+                            return segment === segments[i];
+                        }});
+                        if (matched) {{
+                            return {{route, params}};
+                        }}
+                    }}
+                    return undefined;
+                }}
+
+                function renderRoute(component, route, params, data) {{
+                    const props = route.parameter === undefined ? {{}} : {{[route.parameter]: data}};
+                    return React.createElement(
+                        FsfRouteContext.Provider,
+                        {{value: {{params}}}},
+                        React.createElement(component, props),
+                    );
+                }}
+
+                // Fetches the loader result for a route when navigating on the client
+                async function fetchLoaderData(path) {{
+                    const match = matchRoute(path);
+                    if (!match || !match.route.hasLoader) {{
+                        return undefined;
+                    }}
+                    const response = await fetch(`/__loaders${{path}}`);
+                    if (!response.ok) {{
+                        throw new Error(`Failed to load data for ${{path}}: ${{response.status}}`);
+                    }}
+                    return response.json();
+                }}
+
+                // Swaps in the route component for a path without reloading the page. Paths
+                // the client can't route are left to the server.
+                async function fsfNavigate(path, updateHistory = true) {{
+                    const url = new URL(path, window.location.href);
+                    const match = matchRoute(url.pathname);
+                    if (!fsfRoot || !match || url.origin !== window.location.origin) {{
+                        window.location.assign(url);
+                        return;
+                    }}
+                    const [component, data] = await Promise.all([
+                        match.route.load(),
+                        fetchLoaderData(url.pathname),
+                    ]);
+                    if (updateHistory) {{
+                        window.history.pushState(null, "", url);
+                    }}
+                    fsfRoot.render(renderRoute(component, match.route, match.params, data));
+                }}
+
+                // std::router reaches the router through globals since it can't import it
+                globalThis.fsfNavigate = fsfNavigate;
+                globalThis.FsfRouteContext = FsfRouteContext;
+                {REACT_BOOTSTRAP_FOOTER}"#
+                )
+            }
+            Item::TestRunner => TEST_RUNNER.to_string(),
         }
     }
 
-    fn compile_statement(&mut self, statement: Statement) -> String {
-        match statement {
-            Statement::Print(expr) => format!("console.log({});\n", self.compile_expression(expr)),
+    fn compile_statement(&mut self, (line, statement): (usize, Statement)) -> String {
+        let statement = match statement {
+            Statement::Print(expr) => {
+                let expr = self.compile_expression(expr);
+                format!("{}({});\n", self.helper("fsfPrint"), expr)
+            }
             Statement::Expression(expr) => format!("{}\n", self.compile_expression(expr)),
             Statement::Let {
                 declaration,
@@ -212,30 +596,95 @@ impl JsTarget {
                     },
                 }
             }
-            Statement::AssertEq(left, right) => {
+            Statement::Panic(message) => {
+                format!("throw new Error({});\n", self.compile_expression(message))
+            }
+            Statement::Assert { condition, message } => {
+                let failure =
+                    self.assertion_failure(format!("assert({})", condition.source), message);
                 format!(
-                    "if ({} != {}) {{\nthrow new Error(`{} != {}`);\n}}\n",
-                    self.compile_expression(left.clone()),
-                    self.compile_expression(right.clone()),
-                    // TODO: Replace with source not compiled form
-                    self.compile_expression(left),
-                    self.compile_expression(right),
+                    "if (!({})) {{\nthrow new Error({});\n}}\n",
+                    self.compile_expression(*condition.expression),
+                    failure
+                )
+            }
+            Statement::AssertEq {
+                left,
+                right,
+                message,
+            } => {
+                let failure = self.assertion_failure(
+                    format!("assert_eq({}, {})", left.source, right.source),
+                    message,
+                );
+                let (equal, format) = (self.helper("fsfEqual"), self.helper("fsfFormat"));
+                format!(
+                    "{{\nconst fsfActual = {};\nconst fsfExpected = {};\nif (!{equal}(fsfActual, fsfExpected)) {{\nthrow new Error({} + `\\n  actual: ${{{format}(fsfActual)}}\\nexpected: ${{{format}(fsfExpected)}}`);\n}}\n}}\n",
+                    self.compile_expression(*left.expression),
+                    self.compile_expression(*right.expression),
+                    failure,
+                )
+            }
+            Statement::AssertNe {
+                left,
+                right,
+                message,
+            } => {
+                let failure = self.assertion_failure(
+                    format!("assert_ne({}, {})", left.source, right.source),
+                    message,
+                );
+                let (equal, format) = (self.helper("fsfEqual"), self.helper("fsfFormat"));
+                format!(
+                    "{{\nconst fsfActual = {};\nif ({equal}(fsfActual, {})) {{\nthrow new Error({} + `\\n   value: ${{{format}(fsfActual)}}`);\n}}\n}}\n",
+                    self.compile_expression(*left.expression),
+                    self.compile_expression(*right.expression),
+                    failure,
                 )
             }
             Statement::RunTest {
                 test_name,
                 function_name,
+                options,
             } => {
                 if let Some(Literal::String(test_name)) = test_name.value {
+                    let options = [
+                        options
+                            .setup
+                            .map(|setup| format!("setup: {}", self.compile_expression(*setup))),
+                        options.teardown.map(|teardown| {
+                            format!("teardown: {}", self.compile_expression(*teardown))
+                        }),
+                        options
+                            .ignore
+                            .map(|reason| format!("ignoreReason: {}", string_literal(&reason))),
+                        options.should_panic.map(|expected| {
+                            format!("expectedPanic: {}", string_literal(&expected))
+                        }),
+                    ];
                     format!(
-                        "runTest({}, \"{}\")\n",
+                        "runTest({}, {}, {{{}}})\n",
                         self.compile_expression(*function_name),
-                        test_name
+                        string_literal(&test_name),
+                        options.into_iter().flatten().join(", ")
                     )
                 } else {
                     panic!("Test name must be a string");
                 }
             }
+        };
+        format!("{}{}", line_marker(line), statement)
+    }
+
+    /// The message a failed `assertion` throws, followed by its message if it has one.
+    fn assertion_failure(&mut self, assertion: String, message: Option<Expression>) -> String {
+        match message {
+            Some(message) => format!(
+                "{} + {}",
+                string_literal(&format!("{} failed: ", assertion)),
+                self.compile_expression(message)
+            ),
+            None => string_literal(&format!("{} failed", assertion)),
         }
     }
 
@@ -284,12 +733,32 @@ impl JsTarget {
                 left,
                 operator,
                 right,
-            } => format!(
-                "{} {} {}",
-                self.compile_expression(*left),
-                operator.lexeme,
-                self.compile_expression(*right)
-            ),
+                number_type,
+            } => {
+                let left = self.compile_expression(*left);
+                let right = self.compile_expression(*right);
+                match (number_type, &operator.token_type) {
+                    (Some(NumberType::Int), TokenType::Plus | TokenType::Minus) => {
+                        format!("(({} {} {}) | 0)", left, operator.lexeme, right)
+                    }
+                    (Some(NumberType::Int), TokenType::Star) => {
+                        format!("Math.imul({}, {})", left, right)
+                    }
+                    (Some(NumberType::Int), TokenType::Slash) => {
+                        format!("{}({}, {})", self.helper("fsfDivideInt"), left, right)
+                    }
+                    (
+                        Some(NumberType::I64),
+                        TokenType::Plus | TokenType::Minus | TokenType::Star,
+                    ) => {
+                        format!("BigInt.asIntN(64, {} {} {})", left, operator.lexeme, right)
+                    }
+                    (Some(NumberType::I64), TokenType::Slash) => {
+                        format!("{}({}, {})", self.helper("fsfDivideI64"), left, right)
+                    }
+                    _ => format!("{} {} {}", left, operator.lexeme, right),
+                }
+            }
             ExpressionWithoutBlock::Call { callee, arguments } => {
                 format!(
                     "{}({})",
@@ -301,7 +770,7 @@ impl JsTarget {
                 )
             }
             ExpressionWithoutBlock::Lambda { parameters, body } => {
-                let params = parameters.iter().map(|p| format!("{}", p.name,)).join(", ");
+                let params = parameters.iter().map(|p| p.name.to_string()).join(", ");
                 match *body {
                     Expression::WithoutBlock(expression) => {
                         // TODO: Add proper type inference
@@ -318,64 +787,134 @@ impl JsTarget {
                 format!("({})", self.compile_expression(*expr))
             }
             ExpressionWithoutBlock::Literal(literal) => self.compile_literal(&literal),
-            ExpressionWithoutBlock::Unary { .. } => todo!(),
+            ExpressionWithoutBlock::Unary {
+                operator,
+                right,
+                number_type,
+            } => {
+                // Negating a literal can't overflow, since constants are checked to fit
+                let is_literal = matches!(*right, ExpressionWithoutBlock::Literal(_));
+                let right = self.compile_expression_without_block(*right);
+                let unary = match right.starts_with('-') {
+                    true => format!("{}({})", operator.lexeme, right),
+                    false => format!("{}{}", operator.lexeme, right),
+                };
+                match (number_type, is_literal) {
+                    (Some(NumberType::Int), false) => format!("({} | 0)", unary),
+                    (Some(NumberType::I64), false) => format!("BigInt.asIntN(64, {})", unary),
+                    _ => unary,
+                }
+            }
             ExpressionWithoutBlock::Variable(identifier) => {
+                self.used.insert(identifier.lexeme);
                 format!("{}", identifier.value.unwrap())
             }
+            ExpressionWithoutBlock::Convert {
+                expression,
+                from,
+                to,
+            } => {
+                let expression = self.compile_expression(*expression);
+                match (from, to) {
+                    (Some(from), to) if from == to => expression,
+                    (Some(NumberType::Int), NumberType::Float) => expression,
+                    (Some(NumberType::Int), NumberType::I64) => format!("BigInt({})", expression),
+                    (Some(NumberType::I64), NumberType::Int) => {
+                        format!("Number(BigInt.asIntN(32, {}))", expression)
+                    }
+                    (Some(NumberType::I64), NumberType::Float) => {
+                        format!("Number({})", expression)
+                    }
+                    (Some(NumberType::Float), NumberType::Int) => format!("(({}) | 0)", expression),
+                    (Some(NumberType::Float), NumberType::I64) => {
+                        format!("BigInt(Math.trunc({}))", expression)
+                    }
+                    (_, NumberType::Int) => format!("{}({})", self.helper("fsfToInt"), expression),
+                    (_, NumberType::I64) => format!("{}({})", self.helper("fsfToI64"), expression),
+                    (_, NumberType::Float) => {
+                        format!("{}({})", self.helper("fsfToFloat"), expression)
+                    }
+                }
+            }
             ExpressionWithoutBlock::Assignment {
                 name,
                 value,
                 operator,
-            } => match operator.token_type {
-                TokenType::Equal => {
-                    format!("{} = {}", name.lexeme, self.compile_expression(*value))
-                }
-                TokenType::PlusEqual => {
-                    format!("{} += {}", name.lexeme, self.compile_expression(*value))
+                number_type,
+            } => {
+                let value = self.compile_expression(*value);
+                match (operator.token_type, number_type) {
+                    (TokenType::Equal, _) => format!("{} = {}", name.lexeme, value),
+                    (TokenType::PlusEqual, Some(NumberType::Int)) => {
+                        format!("{0} = ({0} + {1}) | 0", name.lexeme, value)
+                    }
+                    (TokenType::PlusEqual, Some(NumberType::I64)) => {
+                        format!("{0} = BigInt.asIntN(64, {0} + {1})", name.lexeme, value)
+                    }
+                    (TokenType::PlusEqual, _) => format!("{} += {}", name.lexeme, value),
+                    _ => panic!("Unexpected token type in assignment: {}", operator.lexeme),
                 }
-                _ => panic!("Unexpected token type in assignment: {}", operator.lexeme),
-            },
+            }
             ExpressionWithoutBlock::Html {
                 name,
                 inner,
                 attributes,
             } => {
-                let react_attribute_map =
-                    HashMap::from([("onclick".to_string(), "onClick".to_string())]);
+                let react_attribute_map = HashMap::from([
+                    ("onclick".to_string(), "onClick".to_string()),
+                    ("onsubmit".to_string(), "onSubmit".to_string()),
+                ]);
                 let attrs = attributes
                     .into_iter()
                     .map(|(name, expr)| {
                         format!(
-                            "{}={{{}}}",
+                            "{}: {}",
                             react_attribute_map
                                 .get(&name.lexeme)
                                 .unwrap_or(&name.lexeme),
                             self.compile_expression(expr)
                         )
                     })
-                    .join(" ");
+                    .join(", ");
                 let children = inner
                     .into_iter()
-                    .map(|e| match e {
-                        Expression::WithoutBlock(ExpressionWithoutBlock::Html { .. }) => {
-                            self.compile_expression(e)
-                        }
-                        _ => format!("{{{}}}", self.compile_expression(e)),
-                    })
-                    .join("\n");
-                format!("<{} {}>{}</{}>", name.lexeme, attrs, children, name.lexeme)
+                    .map(|e| format!(",\n{}", self.compile_expression(e)))
+                    .join("");
+                // Components are referenced directly, elements by their tag name. Emitting
+                // createElement rather than JSX lets the output run under node as well.
+                let element = match name.lexeme.starts_with(char::is_uppercase) {
+                    true => {
+                        self.used.insert(name.lexeme.clone());
+                        name.lexeme
+                    }
+                    false => format!("\"{}\"", name.lexeme),
+                };
+                format!(
+                    "React.createElement({}, {{{}}}{})",
+                    element, attrs, children
+                )
             }
             ExpressionWithoutBlock::FString { chunks } => {
-                let format_string = chunks
-                    .iter()
+                let template = chunks
+                    .into_iter()
                     .map(|chunk| match chunk {
-                        FStringChunk::Literal(string) => string.to_string(),
-                        FStringChunk::Identifier(string) => format!("${{{string}}}"),
+                        // `${` would start a substitution
+                        FStringChunk::Literal(string) => {
+                            escape(&string, &['`']).replace("${", "\\${")
+                        }
+                        FStringChunk::Expression {
+                            expression,
+                            format,
+                            number_type,
+                        } => {
+                            let value = self.compile_expression(*expression);
+                            format!("${{{}}}", self.format_value(value, &format, number_type))
+                        }
                     })
                     .join("");
-                format!("`{}`", format_string)
+                format!("`{}`", template)
             }
-            ExpressionWithoutBlock::Array { elements } => {
+            ExpressionWithoutBlock::Array { elements, .. } => {
                 let elements = elements
                     .into_iter()
                     .map(|e| self.compile_expression(e))
@@ -419,11 +958,42 @@ impl JsTarget {
 
     fn compile_literal(&mut self, literal: &Literal) -> String {
         match literal {
-            Literal::Number(value) => format!("{}", value),
-            Literal::String(value) => format!("\"{}\"", value),
+            Literal::Integer(value, Some(NumberType::I64)) => format!("{}n", value),
+            Literal::Integer(value, _) => value.to_string(),
+            Literal::Float(value) => format!("{:?}", value),
+            Literal::String(value) => string_literal(value),
             Literal::Identifier(identifier) => identifier.to_string(),
             Literal::True => "true".to_string(),
             Literal::False => "false".to_string(),
         }
     }
 }
+
+/// Reads the value of an action's parameter from the submitted form, converted to the
+/// parameter's type. A checkbox is a `bool` that is only submitted when it is checked.
+fn form_value(parameter: &Parameter) -> String {
+    let value = format!("form.get(\"{}\")", parameter.name);
+    match parameter.type_annotation.as_str() {
+        "bool" => format!("form.has(\"{}\")", parameter.name),
+        // The server reads an i64 from a string, which keeps all of its digits
+        "int" | "float" => format!("Number({})", value),
+        _ => value,
+    }
+}
+
+/// Writes the source map of a generated file next to it, linked with a comment that
+/// node, esbuild and browsers all follow.
+fn write_source_map(
+    output_path: &Path,
+    output_file: &mut File,
+    source_map: &SourceMap,
+) -> Result<()> {
+    let file_name = output_path.file_name().unwrap().to_string_lossy();
+    let map_path = output_path.with_extension("js.map");
+    std::fs::write(
+        &map_path,
+        source_map.to_v3(&file_name, output_path.parent().unwrap()),
+    )?;
+    output_file.write_all(format!("\n//# sourceMappingURL={}.map\n", file_name).as_bytes())?;
+    Ok(())
+}
@@ -1,12 +1,44 @@
 use crate::item::Item;
 use std::path::PathBuf;
 
-#[derive(Clone)]
+#[derive(Clone, Debug)]
 pub struct Module {
     pub path: PathBuf,
     pub items: Vec<Item>,
+    /// The canonical path of the file the module was parsed from, which line directives
+    /// and source maps point at. The standard library's modules are placed in the
+    /// project's `std` directory but read from elsewhere, and generated modules have none.
+    pub source: Option<PathBuf>,
 }
 
 pub type Program = Vec<Module>;
 pub mod go_target;
 pub mod js_target;
+pub mod source_map;
+
+/// A double-quoted string literal that Go and JavaScript both read as `string`. Line
+/// breaks are escaped so that the generated code keeps the lines of the source.
+pub fn string_literal(string: &str) -> String {
+    format!("\"{}\"", escape(string, &['"']))
+}
+
+/// Escapes backslashes, the given delimiters and control characters the way Go and
+/// JavaScript string literals both understand.
+pub fn escape(string: &str, delimiters: &[char]) -> String {
+    let mut escaped = String::with_capacity(string.len());
+    for c in string.chars() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            '\n' => escaped.push_str("\\n"),
+            '\r' => escaped.push_str("\\r"),
+            '\t' => escaped.push_str("\\t"),
+            c if delimiters.contains(&c) => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii_control() => escaped.push_str(&format!("\\x{:02x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,209 @@
+use itertools::Itertools;
+use regex::{Captures, Regex};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Source maps of the generated files, keyed by their canonical path.
+pub type SourceMaps = HashMap<PathBuf, SourceMap>;
+
+/// Marks the start of the code generated for the given fsf source line. Markers are
+/// removed again by `SourceMap::extract` before the generated code is written.
+pub fn line_marker(line: usize) -> String {
+    format!("/*fsf:{}*/", line)
+}
+
+/// Maps a generated line and those following it to a fsf source line, or to nothing
+/// when `source` is `None`.
+#[derive(Debug, Clone)]
+struct Mapping {
+    generated_line: usize,
+    source: Option<(PathBuf, usize)>,
+}
+
+/// Maps the lines of a generated file back to the fsf source they were compiled from.
+#[derive(Debug, Clone, Default)]
+pub struct SourceMap {
+    mappings: Vec<Mapping>,
+}
+
+impl SourceMap {
+    /// Strips the line markers out of code generated from `source`, recording the
+    /// lines they were found on. Code generated without a source isn't mapped.
+    pub fn extract(source: Option<&Path>, code: &str) -> (String, SourceMap) {
+        Self::strip_markers(source, code, false)
+    }
+
+    /// Like `extract`, but also precedes every marked line with a `//line` directive so
+    /// that the Go toolchain reports positions in the fsf source itself.
+    pub fn extract_with_line_directives(source: Option<&Path>, code: &str) -> (String, SourceMap) {
+        Self::strip_markers(source, code, true)
+    }
+
+    fn strip_markers(
+        source: Option<&Path>,
+        code: &str,
+        line_directives: bool,
+    ) -> (String, SourceMap) {
+        let marker = Regex::new(r"/\*fsf:(\d+)\*/").unwrap();
+        let mut source_map = SourceMap::default();
+        let mut lines = Vec::new();
+        for line in code.split('\n') {
+            if let (Some(source), Some(captures)) = (source, marker.captures(line)) {
+                let source_line = captures[1].parse().unwrap();
+                if line_directives {
+                    lines.push(format!("//line {}:{}", source.display(), source_line));
+                }
+                source_map.mappings.push(Mapping {
+                    generated_line: lines.len() + 1,
+                    source: Some((source.to_path_buf(), source_line)),
+                });
+            }
+            lines.push(marker.replace_all(line, "").to_string());
+        }
+
+        // Code appended after this isn't compiled from the source
+        source_map.mappings.push(Mapping {
+            generated_line: lines.len(),
+            source: None,
+        });
+        (lines.join("\n"), source_map)
+    }
+
+    /// Appends the mappings of code that was written after `line_offset` lines.
+    pub fn append(&mut self, other: SourceMap, line_offset: usize) {
+        self.mappings
+            .extend(other.mappings.into_iter().map(|mapping| Mapping {
+                generated_line: mapping.generated_line + line_offset,
+                ..mapping
+            }));
+    }
+
+    /// The fsf source and line the code on a generated line was compiled from.
+    pub fn lookup(&self, generated_line: usize) -> Option<(&Path, usize)> {
+        self.mappings
+            .iter()
+            .take_while(|mapping| mapping.generated_line <= generated_line)
+            .last()
+            .and_then(|mapping| mapping.source.as_ref())
+            .map(|(source, line)| (source.as_path(), *line))
+    }
+
+    /// Encodes the source map in the v3 format, for the generated `file` in `dir`. The
+    /// fsf sources are embedded since browsers can't fetch them from the server.
+    pub fn to_v3(&self, file: &str, dir: &Path) -> String {
+        let sources = self
+            .mappings
+            .iter()
+            .filter_map(|mapping| mapping.source.as_ref().map(|(source, _)| source))
+            .unique()
+            .collect::<Vec<_>>();
+        let last_line = self.mappings.last().map_or(0, |m| m.generated_line);
+
+        // Every generated line maps its first column to the start of its source line,
+        // with each field encoded relative to the previous segment
+        let mut previous = (0, 0);
+        let mappings = (1..=last_line)
+            .map(|generated_line| match self.lookup(generated_line) {
+                Some((source, line)) => {
+                    let source_index = sources.iter().position(|s| *s == source).unwrap() as i64;
+                    let line = line as i64 - 1;
+                    let segment = [0, source_index - previous.0, line - previous.1, 0]
+                        .into_iter()
+                        .map(encode_vlq)
+                        .collect::<String>();
+                    previous = (source_index, line);
+                    segment
+                }
+                // A segment without a source ends the previous mapping
+                None => encode_vlq(0),
+            })
+            .join(";");
+
+        serde_json::json!({
+            "version": 3,
+            "file": file,
+            "sources": sources
+                .iter()
+                .map(|source| relative_path(dir, source).to_string_lossy().to_string())
+                .collect::<Vec<_>>(),
+            "sourcesContent": sources
+                .iter()
+                .map(|source| std::fs::read_to_string(source).ok())
+                .collect::<Vec<_>>(),
+            "names": [],
+            "mappings": mappings,
+        })
+        .to_string()
+    }
+}
+
+/// Encodes a source map field as a base64 variable length quantity.
+fn encode_vlq(value: i64) -> String {
+    const BASE64: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut value = match value < 0 {
+        true => (-value << 1) | 1,
+        false => value << 1,
+    };
+    let mut encoded = String::new();
+    loop {
+        let mut digit = value & 31;
+        value >>= 5;
+        if value > 0 {
+            digit |= 32;
+        }
+        encoded.push(BASE64[digit as usize] as char);
+        if value == 0 {
+            return encoded;
+        }
+    }
+}
+
+/// The path to `to` relative to the directory `from`.
+fn relative_path(from: &Path, to: &Path) -> PathBuf {
+    let from = from.canonicalize().unwrap_or(from.to_path_buf());
+    let to = to.canonicalize().unwrap_or(to.to_path_buf());
+    let common = from
+        .components()
+        .zip(to.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    std::iter::repeat_n("..", from.components().count() - common)
+        .collect::<PathBuf>()
+        .join(to.components().skip(common).collect::<PathBuf>())
+}
+
+/// Rewrites the positions in the output of a toolchain command that point into
+/// generated files so they point at the fsf source instead. Relative paths are
+/// resolved against `cwd`, the directory the command was run in. Sources are shown
+/// relative to the compiler's working directory when they are inside it.
+pub fn map_positions(output: &str, source_maps: &SourceMaps, cwd: &Path) -> String {
+    let position = Regex::new(r"([^\s:()]+\.(?:go|js|fsf)):(\d+)(:\d+)?").unwrap();
+    position
+        .replace_all(output, |captures: &Captures| {
+            let path = cwd.join(&captures[1]);
+            let path = path.canonicalize().unwrap_or(path);
+            let line = captures[2].parse().unwrap();
+            // Go reports positions in the fsf source itself because of the line directives
+            if path.extension().is_some_and(|ext| ext == "fsf") {
+                let column = captures.get(3).map_or("", |column| column.as_str());
+                return format!("{}:{}{}", display_source(&path), line, column);
+            }
+            match source_maps.get(&path).and_then(|map| map.lookup(line)) {
+                Some((source, source_line)) => {
+                    format!("{}:{}", display_source(source), source_line)
+                }
+                None => captures[0].to_string(),
+            }
+        })
+        .to_string()
+}
+
+fn display_source(source: &Path) -> String {
+    let relative = std::env::current_dir().ok().and_then(|cwd| {
+        source
+            .strip_prefix(cwd.canonicalize().ok()?)
+            .ok()
+            .map(Path::to_path_buf)
+    });
+    relative.as_deref().unwrap_or(source).display().to_string()
+}
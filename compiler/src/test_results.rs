@@ -0,0 +1,289 @@
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use serde_json::{json, Value};
+
+/// Precedes each result the test runner writes when `FSF_TEST_FORMAT=json` is set, which
+/// sets results apart from what the tests print themselves.
+const RESULT_PREFIX: &str = "##fsf-test ";
+
+pub struct TestResult {
+    /// The module the test is in, like `std::greeting`.
+    pub module: String,
+    pub name: String,
+    pub passed: bool,
+    /// Whether the test is marked `#[ignore]` and wasn't run.
+    pub ignored: bool,
+    /// How long the test ran for, in seconds.
+    pub duration: f64,
+    /// Why the test failed or was ignored, which is empty when it passed.
+    pub message: String,
+}
+
+/// The results of running the tests on a target.
+pub struct TestRun {
+    pub target: String,
+    pub results: Vec<TestResult>,
+}
+
+/// A test that didn't pass on every target, or failed on every target but wasn't run
+/// on all of them.
+pub struct Difference<'a> {
+    pub module: &'a str,
+    pub name: &'a str,
+    /// The result on each target, in the order of the runs. A test is missing from a run
+    /// when the tests crashed before getting to it.
+    pub results: Vec<Option<&'a TestResult>>,
+}
+
+impl TestResult {
+    /// Parses a line of the test runner's output, which is `None` unless the line is a
+    /// result.
+    pub fn parse(line: &str) -> Result<Option<TestResult>> {
+        let Some(result) = line.strip_prefix(RESULT_PREFIX) else {
+            return Ok(None);
+        };
+        let result: Value = serde_json::from_str(result)?;
+        let invalid = || anyhow!("Invalid test result: {}", line);
+
+        let full_name = result["name"].as_str().ok_or_else(invalid)?;
+        let (module, name) = full_name.rsplit_once("::").unwrap_or(("", full_name));
+        Ok(Some(TestResult {
+            module: module.to_string(),
+            name: name.to_string(),
+            passed: result["passed"].as_bool().ok_or_else(invalid)?,
+            ignored: result["ignored"].as_bool().unwrap_or_default(),
+            duration: result["duration"].as_f64().ok_or_else(invalid)?,
+            message: result["message"].as_str().unwrap_or_default().to_string(),
+        }))
+    }
+
+    pub fn status(&self) -> &'static str {
+        match (self.passed, self.ignored) {
+            (_, true) => "ignored",
+            (true, _) => "passed",
+            (false, _) => "failed",
+        }
+    }
+}
+
+/// The number of tests that passed, failed and were ignored.
+fn counts(results: &[TestResult]) -> (usize, usize, usize) {
+    let count = |status| {
+        results
+            .iter()
+            .filter(|result| result.status() == status)
+            .count()
+    };
+    (count("passed"), count("failed"), count("ignored"))
+}
+
+/// The tests whose results differ between the runs.
+pub fn differences(runs: &[TestRun]) -> Vec<Difference<'_>> {
+    runs.iter()
+        .flat_map(|run| &run.results)
+        .map(|result| (result.module.as_str(), result.name.as_str()))
+        .unique()
+        .filter_map(|(module, name)| {
+            let results = runs
+                .iter()
+                .map(|run| {
+                    run.results
+                        .iter()
+                        .find(|result| result.module == module && result.name == name)
+                })
+                .collect::<Vec<_>>();
+            let is_consistent = results.iter().all(|result| {
+                result.is_some_and(|result| {
+                    Some(result.status()) == results[0].map(|result| result.status())
+                })
+            });
+            match is_consistent {
+                true => None,
+                false => Some(Difference {
+                    module,
+                    name,
+                    results,
+                }),
+            }
+        })
+        .collect()
+}
+
+/// A summary of each run followed by the tests whose results differ between them.
+pub fn comparison_report(runs: &[TestRun], differences: &[Difference]) -> String {
+    let mut report = runs
+        .iter()
+        .map(|run| {
+            let (passed, failed, ignored) = counts(&run.results);
+            match ignored {
+                0 => format!("{}: {} passed, {} failed\n", run.target, passed, failed),
+                _ => format!(
+                    "{}: {} passed, {} failed, {} ignored\n",
+                    run.target, passed, failed, ignored
+                ),
+            }
+        })
+        .join("");
+
+    if !differences.is_empty() {
+        report.push_str(&format!(
+            "\n{} test(s) differ between targets:\n",
+            differences.len()
+        ));
+    }
+    for difference in differences {
+        report.push_str(&format!("{}::{}\n", difference.module, difference.name));
+        for (run, result) in runs.iter().zip(&difference.results) {
+            match result {
+                Some(result) if result.ignored => {
+                    report.push_str(&format!("    {}: ignored\n", run.target))
+                }
+                Some(result) if result.passed => {
+                    report.push_str(&format!("    {}: pass\n", run.target))
+                }
+                Some(result) => report.push_str(&format!(
+                    "    {}: fail\n        {}\n",
+                    run.target,
+                    result.message.replace('\n', "\n        ")
+                )),
+                None => report.push_str(&format!("    {}: not run\n", run.target)),
+            }
+        }
+    }
+    report
+}
+
+pub fn json_report(runs: &[TestRun], differences: &[Difference]) -> String {
+    let report = match runs {
+        [run] => json_results(&run.results),
+        _ => {
+            let targets = runs
+                .iter()
+                .map(|run| (run.target.clone(), json_results(&run.results)))
+                .collect::<serde_json::Map<_, _>>();
+            let differences = differences
+                .iter()
+                .map(|difference| {
+                    let results = runs
+                        .iter()
+                        .zip(&difference.results)
+                        .map(|(run, result)| {
+                            let status = match result {
+                                Some(result) => result.status(),
+                                None => "not run",
+                            };
+                            (run.target.clone(), json!(status))
+                        })
+                        .collect::<serde_json::Map<_, _>>();
+                    json!({
+                        "module": difference.module,
+                        "name": difference.name,
+                        "results": results,
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "targets": targets,
+                "differences": differences,
+            })
+        }
+    };
+    serde_json::to_string_pretty(&report).unwrap()
+}
+
+fn json_results(results: &[TestResult]) -> Value {
+    let tests = results
+        .iter()
+        .map(|result| {
+            json!({
+                "module": result.module,
+                "name": result.name,
+                "status": result.status(),
+                "duration": result.duration,
+                "message": result.message,
+            })
+        })
+        .collect::<Vec<_>>();
+    let (passed, failed, ignored) = counts(results);
+
+    json!({
+        "passed": passed,
+        "failed": failed,
+        "ignored": ignored,
+        "tests": tests,
+    })
+}
+
+/// A JUnit XML report with a test suite for each module. When there are several runs
+/// the suites are named after the target as well, like `go::module`.
+pub fn junit_report(runs: &[TestRun]) -> String {
+    let results = runs.iter().flat_map(|run| &run.results).collect::<Vec<_>>();
+    let suites = runs
+        .iter()
+        .flat_map(|run| {
+            run.results
+                .iter()
+                .chunk_by(|result| &result.module)
+                .into_iter()
+                .map(|(module, tests)| {
+                    let suite = match runs.len() {
+                        1 => module.clone(),
+                        _ => format!("{}::{}", run.target, module),
+                    };
+                    (suite, tests.collect::<Vec<_>>())
+                })
+                .collect::<Vec<_>>()
+        })
+        .map(|(module, tests)| {
+            let cases = tests
+                .iter()
+                .map(|test| {
+                    let failure = match test.status() {
+                        "failed" => format!(
+                            "\n      <failure message=\"{}\">{}</failure>\n    ",
+                            escape_xml(test.message.lines().next().unwrap_or_default()),
+                            escape_xml(&test.message)
+                        ),
+                        "ignored" => format!(
+                            "\n      <skipped message=\"{}\"/>\n    ",
+                            escape_xml(&test.message)
+                        ),
+                        _ => "".to_string(),
+                    };
+                    format!(
+                        "    <testcase name=\"{}\" classname=\"{}\" time=\"{:.3}\">{}</testcase>\n",
+                        escape_xml(&test.name),
+                        escape_xml(&module),
+                        test.duration,
+                        failure
+                    )
+                })
+                .join("");
+            format!(
+                "  <testsuite name=\"{}\" tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n{}  </testsuite>\n",
+                escape_xml(&module),
+                tests.len(),
+                tests.iter().filter(|test| test.status() == "failed").count(),
+                tests.iter().filter(|test| test.ignored).count(),
+                tests.iter().map(|test| test.duration).sum::<f64>(),
+                cases
+            )
+        })
+        .join("");
+
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<testsuites tests=\"{}\" failures=\"{}\" skipped=\"{}\" time=\"{:.3}\">\n{}</testsuites>\n",
+        results.len(),
+        results.iter().filter(|result| result.status() == "failed").count(),
+        results.iter().filter(|result| result.ignored).count(),
+        results.iter().map(|result| result.duration).sum::<f64>(),
+        suites
+    )
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
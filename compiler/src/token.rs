@@ -1,10 +1,42 @@
+/// The types of numbers. `int` is a 32-bit and `i64` a 64-bit signed integer, which
+/// wrap around on overflow and divide by truncating on every target. `float` is a
+/// 64-bit floating point number.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum NumberType {
+    Int,
+    I64,
+    Float,
+}
+
+impl NumberType {
+    pub fn from_name(name: &str) -> Option<NumberType> {
+        match name {
+            "int" => Some(NumberType::Int),
+            "i64" => Some(NumberType::I64),
+            "float" => Some(NumberType::Float),
+            _ => None,
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            NumberType::Int => "int",
+            NumberType::I64 => "i64",
+            NumberType::Float => "float",
+        }
+    }
+}
+
 #[derive(Clone, Debug)]
 pub enum Literal {
     True,
     False,
     Identifier(String),
     String(String),
-    Number(f64),
+    /// An integer literal. Its type is given by a suffix, like `1i64` or `1float`, or
+    /// else by where it's used, and is filled in by the `NumberTransformer`.
+    Integer(u64, Option<NumberType>),
+    Float(f64),
 }
 
 impl std::fmt::Display for Literal {
@@ -14,7 +46,8 @@ impl std::fmt::Display for Literal {
             Self::False => write!(f, "false"),
             Self::Identifier(identifier) => write!(f, "{}", identifier),
             Self::String(s) => write!(f, "{}", s),
-            Self::Number(n) => write!(f, "{}", n),
+            Self::Integer(n, _) => write!(f, "{}", n),
+            Self::Float(n) => write!(f, "{:?}", n),
         }
     }
 }
@@ -30,6 +63,7 @@ pub enum TokenType {
     RightSquareBracket,
     Comma,
     Dot,
+    Hash,
     Minus,
     Pipe,
     Plus,
@@ -58,6 +92,10 @@ pub enum TokenType {
     String,
     Number,
     FString,
+    Comment,
+    /// A `///` comment, whose value is its text. It documents the item or struct field
+    /// after it.
+    DocComment,
 
     // Keywords.
     Let,
@@ -69,9 +107,13 @@ pub enum TokenType {
     Cmpnt,
     If,
     Else,
+    Assert,
     AssertEq,
+    AssertNe,
+    Panic,
     Import,
     Struct,
+    Loader,
 
     // Builtins.
     RunTest,
@@ -81,7 +123,7 @@ pub enum TokenType {
     RawJs,
     RawGo,
 
-    EOF,
+    Eof,
 }
 
 #[derive(Clone, Debug)]
@@ -89,7 +131,9 @@ pub struct Token {
     pub token_type: TokenType,
     pub lexeme: String,
     pub value: Option<Literal>,
-    line: usize,
+    pub line: usize,
+    /// The column the token starts at, counting from 0.
+    pub column: usize,
 }
 
 impl Token {
@@ -104,6 +148,7 @@ impl Token {
             lexeme,
             value,
             line,
+            column: 0,
         }
     }
 }
@@ -1,4 +1,6 @@
-use crate::expression::{BlockExpression, Expression, ExpressionWithBlock, ExpressionWithoutBlock};
+use crate::expression::{
+    BlockExpression, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk,
+};
 use crate::item::Item;
 use crate::statement::Statement;
 use crate::targets::{Module, Program};
@@ -24,15 +26,18 @@ fn walk_item(item: &mut Item, visitor: &mut impl AstVisitor) {
 
     match item {
         Item::Function { body, .. } => walk_block(body, visitor),
+        Item::Action { body, .. } => walk_block(body, visitor),
+        Item::Loader { body, .. } => walk_block(body, visitor),
         Item::Component { body, .. } => walk_block(body, visitor),
         Item::Struct { .. } => (),
         Item::Import { .. } => (),
         Item::TestRunner => (),
+        Item::Router { .. } => (),
     }
 }
 
 fn walk_block(block: &mut BlockExpression, visitor: &mut impl AstVisitor) {
-    for statement in &mut block.statements {
+    for (_, statement) in &mut block.statements {
         walk_statement(statement, visitor);
     }
     if let Some(expr) = &mut block.expr {
@@ -47,10 +52,38 @@ fn walk_statement(statement: &mut Statement, visitor: &mut impl AstVisitor) {
         Statement::Print(expression) => walk_expression(expression, visitor),
         Statement::Expression(expression) => walk_expression(expression, visitor),
         Statement::Let { expression, .. } => walk_expression(expression, visitor),
-        Statement::RunTest { function_name, .. } => walk_expression(function_name, visitor),
-        Statement::AssertEq(left, right) => {
-            walk_expression(left, visitor);
-            walk_expression(right, visitor);
+        Statement::RunTest {
+            function_name,
+            options,
+            ..
+        } => {
+            walk_expression(function_name, visitor);
+            for hook in options.setup.iter_mut().chain(&mut options.teardown) {
+                walk_expression(hook, visitor);
+            }
+        }
+        Statement::Panic(message) => walk_expression(message, visitor),
+        Statement::Assert { condition, message } => {
+            walk_expression(&mut condition.expression, visitor);
+            if let Some(message) = message {
+                walk_expression(message, visitor);
+            }
+        }
+        Statement::AssertEq {
+            left,
+            right,
+            message,
+        }
+        | Statement::AssertNe {
+            left,
+            right,
+            message,
+        } => {
+            walk_expression(&mut left.expression, visitor);
+            walk_expression(&mut right.expression, visitor);
+            if let Some(message) = message {
+                walk_expression(message, visitor);
+            }
         }
     }
 }
@@ -106,7 +139,13 @@ fn walk_expression_without_block(expr: &mut ExpressionWithoutBlock, visitor: &mu
         ExpressionWithoutBlock::Assignment { value, .. } => {
             walk_expression_without_block(value, visitor)
         }
-        ExpressionWithoutBlock::Html { inner, .. } => {
+        ExpressionWithoutBlock::Convert { expression, .. } => walk_expression(expression, visitor),
+        ExpressionWithoutBlock::Html {
+            attributes, inner, ..
+        } => {
+            for (_, expression) in attributes.iter_mut() {
+                walk_expression(expression, visitor)
+            }
             for expression in inner {
                 walk_expression(expression, visitor)
             }
@@ -126,11 +165,17 @@ fn walk_expression_without_block(expr: &mut ExpressionWithoutBlock, visitor: &mu
                 walk_expression(expression, visitor)
             }
         }
+        ExpressionWithoutBlock::FString { chunks } => {
+            for chunk in chunks {
+                if let FStringChunk::Expression { expression, .. } = chunk {
+                    walk_expression(expression, visitor)
+                }
+            }
+        }
 
         // NO OPS
         ExpressionWithoutBlock::Literal(_) => (),
         ExpressionWithoutBlock::Variable(_) => (),
-        ExpressionWithoutBlock::FString { .. } => (),
         ExpressionWithoutBlock::RawJs(_) => (),
         ExpressionWithoutBlock::RawGo(_) => (),
     }
@@ -25,56 +25,67 @@ impl JsIdentifierTransformer {
     pub fn transform(&mut self, program: &mut Program) {
         walk_ast(program, self)
     }
+
+    /// The name of an item of the current module, prefixed with the module's path.
+    fn item_name(&self, name: &str) -> String {
+        format!(
+            "{}_{}",
+            self.current_module
+                .as_ref()
+                .unwrap()
+                .with_extension("")
+                .strip_prefix(&self.root)
+                .unwrap()
+                .iter()
+                .map(|p| p.to_string_lossy())
+                .join("_"),
+            name
+        )
+    }
+
+    /// The name of an imported item, prefixed with the path of the module it's from.
+    fn import_name(&self, path: &[String]) -> String {
+        self.current_module
+            .as_ref()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .strip_prefix(&self.root)
+            .iter()
+            .map(|p| p.to_string_lossy())
+            .filter(|p| !p.is_empty())
+            .chain(path.iter().map(std::borrow::Cow::from))
+            .join("_")
+    }
 }
 
 impl AstVisitor for JsIdentifierTransformer {
     fn visit_module(&mut self, module: &mut Module) {
         self.current_module = Some(module.path.clone());
         self.name_map.clear();
-    }
 
-    fn visit_item(&mut self, item: &mut Item) {
-        match item {
-            Item::Function { name, .. } => {
+        // Every name is collected before any is renamed, so that items can be used
+        // before they are declared
+        for item in &module.items {
+            match item {
                 // TODO: Add transformer step to find main function and add bootstrapping
-                if name == "main" {
-                    return;
+                Item::Function { name, .. } | Item::Action { name, .. } if name != "main" => {
+                    self.name_map.insert(name.clone(), self.item_name(name));
                 }
-
-                let new_name = format!(
-                    "{}_{}",
-                    self.current_module
-                        .as_ref()
-                        .unwrap()
-                        .with_extension("")
-                        .strip_prefix(&self.root)
-                        .unwrap()
-                        .iter()
-                        .map(|p| p.to_string_lossy())
-                        .join("_"),
-                    name
-                );
-                self.name_map.insert(name.clone(), new_name.clone());
-                name.clear();
-                name.push_str(&new_name);
+                Item::Import { path } => {
+                    let name = path.last().unwrap().to_string();
+                    self.name_map.insert(name, self.import_name(path));
+                }
+                _ => (),
             }
-            Item::Import { path } => {
-                let name = path.last().unwrap().to_string();
-                let full_path = self
-                    .current_module
-                    .as_ref()
-                    .unwrap()
-                    .parent()
-                    .unwrap()
-                    .strip_prefix(&self.root)
-                    .iter()
-                    .map(|p| p.to_string_lossy())
-                    .filter(|p| !p.is_empty())
-                    .chain(path.iter().map(std::borrow::Cow::from))
-                    .join("_");
-                self.name_map.insert(name, full_path);
+        }
+    }
+
+    fn visit_item(&mut self, item: &mut Item) {
+        if let Item::Function { name, .. } | Item::Action { name, .. } = item {
+            if name != "main" {
+                *name = self.item_name(name);
             }
-            _ => (),
         }
     }
 
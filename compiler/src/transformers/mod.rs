@@ -1,9 +1,13 @@
 mod ast_visitor;
 mod identifier_transformer;
+mod number_transformer;
+mod router_transformer;
 mod stdlib_transformer;
 mod test_runner_transformer;
 
 use ast_visitor::*;
 pub use identifier_transformer::*;
+pub use number_transformer::*;
+pub use router_transformer::*;
 pub use stdlib_transformer::*;
 pub use test_runner_transformer::*;
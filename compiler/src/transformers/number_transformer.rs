@@ -0,0 +1,951 @@
+use crate::expression::{
+    BlockExpression, Expression, ExpressionWithBlock, ExpressionWithoutBlock, FStringChunk,
+};
+use crate::item::{Item, Parameter};
+use crate::statement::{Declaration, Statement};
+use crate::targets::Program;
+use crate::token::{Literal, NumberType, Token, TokenType};
+use anyhow::{anyhow, Result};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+/// The type of an expression, as far as numbers are concerned.
+#[derive(Debug, Clone, PartialEq)]
+enum Type {
+    Number(NumberType),
+    /// Integer literals without a suffix, and arithmetic on them, take the type of
+    /// the numbers they're used with, or `int` if there are none. Arithmetic on them
+    /// is on integers even when it's used as a float.
+    UntypedInt,
+    Array(Box<Type>),
+    Struct(String),
+    Unknown,
+}
+
+struct Signature {
+    parameters: Vec<(String, Type)>,
+    return_type: Type,
+}
+
+/// Gives every number its type, so that the targets can generate arithmetic that
+/// behaves the same on Go and JavaScript. Numbers of different types can't be mixed,
+/// they're converted with `int(x)`, `i64(x)` and `float(x)`.
+#[derive(Default)]
+pub struct NumberTransformer {
+    /// The functions and components of each module, by name.
+    signatures: HashMap<PathBuf, HashMap<String, Signature>>,
+    /// The fields of every struct, by the struct's name.
+    structs: HashMap<String, Vec<(String, String)>>,
+    /// The signatures visible in the module being transformed, as `(module, name)`.
+    visible: HashMap<String, (PathBuf, String)>,
+    scopes: Vec<HashMap<String, Type>>,
+    module: PathBuf,
+    line: usize,
+    /// The module, line and message of every error.
+    errors: Vec<(PathBuf, usize, String)>,
+}
+
+impl NumberTransformer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn transform(&mut self, program: &mut Program) -> Result<()> {
+        match self.check(program).as_slice() {
+            [] => Ok(()),
+            errors => Err(anyhow!(errors
+                .iter()
+                .map(|(module, line, message)| format!(
+                    "{}:{}: {}",
+                    module.display(),
+                    line,
+                    message
+                ))
+                .join("\n"))),
+        }
+    }
+
+    /// Types the numbers of a program like `transform`, and returns the errors found
+    /// instead of failing.
+    pub fn check(&mut self, program: &mut Program) -> Vec<(PathBuf, usize, String)> {
+        for module in program.iter() {
+            for item in &module.items {
+                if let Item::Struct { name, fields, .. } = item {
+                    let fields = fields
+                        .iter()
+                        .map(|field| (field.name.clone(), field.type_annotation.clone()))
+                        .collect();
+                    self.structs.insert(name.clone(), fields);
+                }
+            }
+        }
+        for module in program.iter() {
+            let signatures = module
+                .items
+                .iter()
+                .filter_map(|item| match item {
+                    Item::Function {
+                        name,
+                        parameters,
+                        return_type,
+                        ..
+                    }
+                    | Item::Action {
+                        name,
+                        parameters,
+                        return_type,
+                        ..
+                    } => Some((name, self.signature(parameters, return_type.as_deref()))),
+                    Item::Component {
+                        name, parameters, ..
+                    } => Some((name, self.signature(parameters, None))),
+                    _ => None,
+                })
+                .map(|(name, signature)| (name.clone(), signature))
+                .collect();
+            self.signatures.insert(module.path.clone(), signatures);
+        }
+
+        for module in program.iter_mut() {
+            self.module = module.path.clone();
+            self.visible = self.visible_signatures(&module.items);
+            for item in &mut module.items {
+                self.item(item);
+            }
+        }
+
+        std::mem::take(&mut self.errors)
+    }
+
+    fn signature(&self, parameters: &[Parameter], return_type: Option<&str>) -> Signature {
+        Signature {
+            parameters: parameters
+                .iter()
+                .map(|p| {
+                    (
+                        p.name.clone(),
+                        self.annotated_type(Some(&p.type_annotation)),
+                    )
+                })
+                .collect(),
+            return_type: self.annotated_type(return_type),
+        }
+    }
+
+    /// Every module compiles into the same Go package, so the items of every module are
+    /// visible, but a module's own items and its imports come first.
+    fn visible_signatures(&self, items: &[Item]) -> HashMap<String, (PathBuf, String)> {
+        let mut visible = HashMap::new();
+        for (module, signatures) in &self.signatures {
+            for name in signatures.keys() {
+                visible.insert(name.clone(), (module.clone(), name.clone()));
+            }
+        }
+        for name in self.signatures[&self.module].keys() {
+            visible.insert(name.clone(), (self.module.clone(), name.clone()));
+        }
+        for item in items {
+            if let Item::Import { path } = item {
+                let (name, module) = path.split_last().unwrap();
+                let module = self
+                    .module
+                    .parent()
+                    .unwrap()
+                    .join(module.join("/"))
+                    .with_extension("fsf");
+                visible.insert(name.clone(), (module, name.clone()));
+            }
+        }
+        visible
+    }
+
+    fn lookup_signature(&self, name: &str) -> Option<&Signature> {
+        let (module, name) = self.visible.get(name)?;
+        self.signatures.get(module)?.get(name)
+    }
+
+    fn annotated_type(&self, annotation: Option<&str>) -> Type {
+        match annotation {
+            Some(annotation) => match NumberType::from_name(annotation) {
+                Some(number_type) => Type::Number(number_type),
+                None if self.structs.contains_key(annotation) => {
+                    Type::Struct(annotation.to_string())
+                }
+                None => Type::Unknown,
+            },
+            None => Type::Unknown,
+        }
+    }
+
+    fn error(&mut self, line: usize, message: String) {
+        let error = (self.module.clone(), line, message);
+        if !self.errors.contains(&error) {
+            self.errors.push(error);
+        }
+    }
+
+    fn item(&mut self, item: &mut Item) {
+        let (parameters, body, return_type) = match item {
+            Item::Function {
+                parameters,
+                body,
+                return_type,
+                ..
+            }
+            | Item::Action {
+                parameters,
+                body,
+                return_type,
+                ..
+            } => (parameters.as_slice(), body, return_type.as_deref()),
+            Item::Component {
+                parameters, body, ..
+            } => (parameters.as_slice(), body, None),
+            Item::Loader {
+                body, return_type, ..
+            } => (&[][..], body, Some(return_type.as_str())),
+            _ => return,
+        };
+
+        let scope = parameters
+            .iter()
+            .map(|p| {
+                (
+                    p.name.clone(),
+                    self.annotated_type(Some(&p.type_annotation)),
+                )
+            })
+            .collect();
+        let return_type = self.annotated_type(return_type);
+        self.scopes = vec![scope];
+        self.line = body.line;
+        let actual = self.block(body);
+        if let Some(expr) = &mut body.expr {
+            self.coerce(expr, actual, &return_type);
+        }
+    }
+
+    fn block(&mut self, block: &mut BlockExpression) -> Type {
+        self.scopes.push(HashMap::new());
+        for (line, statement) in &mut block.statements {
+            self.line = *line;
+            self.statement(statement);
+        }
+        let actual = match &mut block.expr {
+            Some(expr) => self.expression_without_block(expr),
+            None => Type::Unknown,
+        };
+        self.scopes.pop();
+        actual
+    }
+
+    fn statement(&mut self, statement: &mut Statement) {
+        match statement {
+            Statement::Print(expr) | Statement::Expression(expr) | Statement::Panic(expr) => {
+                self.expression_with_default(expr);
+            }
+            Statement::Let {
+                declaration,
+                expression,
+                number_type,
+                ..
+            } => {
+                let actual = self.expression_with_default(expression);
+                match declaration {
+                    Declaration::Name(name) => {
+                        if let Type::Number(actual) = actual {
+                            *number_type = Some(actual);
+                        }
+                        self.declare(name, actual);
+                    }
+                    Declaration::Array(names) => {
+                        let element = match actual {
+                            Type::Array(element) => *element,
+                            _ => Type::Unknown,
+                        };
+                        for name in names {
+                            self.declare(name, element.clone());
+                        }
+                    }
+                    Declaration::Tuple(names) => {
+                        for name in names {
+                            self.declare(name, Type::Unknown);
+                        }
+                    }
+                }
+            }
+            Statement::Assert { condition, message } => {
+                self.expression_with_default(&mut condition.expression);
+                if let Some(message) = message {
+                    self.expression_with_default(message);
+                }
+            }
+            Statement::AssertEq {
+                left,
+                right,
+                message,
+            }
+            | Statement::AssertNe {
+                left,
+                right,
+                message,
+            } => {
+                let actual = self.unify(&mut left.expression, &mut right.expression, "comparison");
+                if actual == Type::UntypedInt {
+                    left.expression.set_number_type(NumberType::Int);
+                    right.expression.set_number_type(NumberType::Int);
+                }
+                if let Some(message) = message {
+                    self.expression_with_default(message);
+                }
+            }
+            Statement::RunTest { .. } => {}
+        }
+    }
+
+    fn declare(&mut self, name: &Token, actual: Type) {
+        self.scopes
+            .last_mut()
+            .unwrap()
+            .insert(name.lexeme.clone(), actual);
+    }
+
+    fn lookup(&self, name: &str) -> Type {
+        self.scopes
+            .iter()
+            .rev()
+            .find_map(|scope| scope.get(name))
+            .cloned()
+            .unwrap_or(Type::Unknown)
+    }
+
+    fn expression(&mut self, expr: &mut Expression) -> Type {
+        match expr {
+            Expression::WithBlock(expr) => self.expression_with_block(expr),
+            Expression::WithoutBlock(expr) => self.expression_without_block(expr),
+        }
+    }
+
+    /// Infers the type of an expression that isn't used with other numbers.
+    fn expression_with_default(&mut self, expr: &mut Expression) -> Type {
+        let actual = self.expression(expr);
+        self.coerce(expr, actual, &Type::Unknown)
+    }
+
+    /// Gives an untyped integer the expected type, or `int` when no number is expected,
+    /// and reports numbers of a different type than expected.
+    fn coerce(&mut self, expr: &mut impl Numeric, actual: Type, expected: &Type) -> Type {
+        let actual = match (actual, expected) {
+            (Type::UntypedInt, Type::Number(expected)) => {
+                expr.set_number_type(*expected);
+                Type::Number(*expected)
+            }
+            (Type::UntypedInt, _) => {
+                expr.set_number_type(NumberType::Int);
+                Type::Number(NumberType::Int)
+            }
+            (Type::Number(actual), Type::Number(expected)) if actual != *expected => {
+                self.error(
+                    self.line,
+                    format!(
+                        "mismatched types: expected {}, found {}",
+                        expected.name(),
+                        actual.name()
+                    ),
+                );
+                return Type::Number(actual);
+            }
+            (actual, _) => actual,
+        };
+
+        if let Type::Number(number_type) = actual {
+            self.check_constant(&*expr, number_type);
+        }
+        actual
+    }
+
+    /// Constant arithmetic is checked at compile time, as Go does, rather than wrapping.
+    fn check_constant(&mut self, expr: &impl Numeric, number_type: NumberType) {
+        let (min, max) = match number_type {
+            NumberType::Int => (i32::MIN as i128, i32::MAX as i128),
+            NumberType::I64 => (i64::MIN as i128, i64::MAX as i128),
+            NumberType::Float => return,
+        };
+        if let Some(value) = expr.constant().filter(|value| *value < min || *value > max) {
+            self.error(
+                self.line,
+                format!("constant {} overflows {}", value, number_type.name()),
+            );
+        }
+    }
+
+    /// Infers the types of two numbers used together, giving an untyped one the type of
+    /// the other.
+    fn unify(
+        &mut self,
+        left: &mut impl NumericExpression,
+        right: &mut impl NumericExpression,
+        operation: &str,
+    ) -> Type {
+        let left_type = left.infer(self);
+        let right_type = right.infer(self);
+        match (left_type, right_type) {
+            (Type::Number(l), Type::Number(r)) if l != r => {
+                self.error(
+                    self.line,
+                    format!(
+                        "mismatched types {} and {} in {}",
+                        l.name(),
+                        r.name(),
+                        operation
+                    ),
+                );
+                Type::Number(l)
+            }
+            (Type::Number(number_type), other) => {
+                self.coerce(right, other, &Type::Number(number_type))
+            }
+            (other, Type::Number(number_type)) => {
+                self.coerce(left, other, &Type::Number(number_type))
+            }
+            (Type::UntypedInt, Type::UntypedInt) => Type::UntypedInt,
+            (left_type, right_type) => {
+                self.coerce(left, left_type, &Type::Unknown);
+                self.coerce(right, right_type, &Type::Unknown);
+                Type::Unknown
+            }
+        }
+    }
+
+    fn expression_with_block(&mut self, expr: &mut ExpressionWithBlock) -> Type {
+        match expr {
+            ExpressionWithBlock::Block(block) => self.block(block),
+            ExpressionWithBlock::If { expr, then, r#else } => {
+                self.expression_with_default(expr);
+                let mut then = BranchValue(then);
+                match r#else {
+                    Some(r#else) => self.unify(&mut then, r#else.as_mut(), "if and else"),
+                    None => {
+                        let actual = then.infer(self);
+                        self.coerce(&mut then, actual, &Type::Unknown)
+                    }
+                }
+            }
+        }
+    }
+
+    fn expression_without_block(&mut self, expr: &mut ExpressionWithoutBlock) -> Type {
+        let actual = self.infer_without_block(expr);
+        if let Type::Number(number_type) = actual {
+            self.check_constant(expr, number_type);
+        }
+        actual
+    }
+
+    fn infer_without_block(&mut self, expr: &mut ExpressionWithoutBlock) -> Type {
+        match expr {
+            ExpressionWithoutBlock::Literal(Literal::Integer(_, Some(number_type))) => {
+                Type::Number(*number_type)
+            }
+            ExpressionWithoutBlock::Literal(Literal::Integer(_, None)) => Type::UntypedInt,
+            ExpressionWithoutBlock::Literal(Literal::Float(_)) => Type::Number(NumberType::Float),
+            ExpressionWithoutBlock::Literal(_) => Type::Unknown,
+            ExpressionWithoutBlock::Variable(name) => self.lookup(&name.lexeme),
+            ExpressionWithoutBlock::Grouping(expr) => self.expression_without_block(expr),
+            ExpressionWithoutBlock::Binary {
+                left,
+                operator,
+                right,
+                number_type,
+            } => {
+                self.line = operator.line;
+                let operation = format!("'{}'", operator.lexeme);
+                let actual = self.unify(left.as_mut(), right.as_mut(), &operation);
+                let is_arithmetic = matches!(
+                    operator.token_type,
+                    TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash
+                );
+                if let Type::Number(actual) = actual {
+                    *number_type = Some(actual);
+                }
+                if operator.token_type == TokenType::Slash && right.is_zero() {
+                    self.error(operator.line, "division by zero".to_string());
+                }
+                match (is_arithmetic, actual) {
+                    (true, actual) => actual,
+                    (false, Type::UntypedInt) => {
+                        left.set_number_type(NumberType::Int);
+                        right.set_number_type(NumberType::Int);
+                        *number_type = Some(NumberType::Int);
+                        Type::Unknown
+                    }
+                    (false, _) => Type::Unknown,
+                }
+            }
+            ExpressionWithoutBlock::Unary {
+                operator,
+                right,
+                number_type,
+            } => {
+                let actual = self.expression_without_block(right);
+                match (&operator.token_type, actual) {
+                    (TokenType::Minus, Type::Number(actual)) => {
+                        *number_type = Some(actual);
+                        Type::Number(actual)
+                    }
+                    (TokenType::Minus, Type::UntypedInt) => Type::UntypedInt,
+                    (_, actual) => {
+                        self.coerce(right.as_mut(), actual, &Type::Unknown);
+                        Type::Unknown
+                    }
+                }
+            }
+            ExpressionWithoutBlock::Call { callee, arguments } => {
+                let name = match callee.as_ref() {
+                    ExpressionWithoutBlock::Variable(name) => Some(name.lexeme.clone()),
+                    _ => None,
+                };
+                let conversion = name
+                    .as_deref()
+                    .filter(|name| self.lookup_signature(name).is_none())
+                    .and_then(NumberType::from_name);
+                if let (Some(to), [_]) = (conversion, arguments.as_slice()) {
+                    let mut expression = arguments.pop().unwrap();
+                    let from = match self.expression(&mut expression) {
+                        Type::Number(from) => Some(from),
+                        Type::UntypedInt => {
+                            expression.set_number_type(to);
+                            Some(to)
+                        }
+                        _ => None,
+                    };
+                    // Go doesn't allow converting constants that don't fit the type, so
+                    // conversions of constants are done here
+                    if let Some(converted) = convert_constant(&expression, to) {
+                        *expr = converted;
+                        return Type::Number(to);
+                    }
+                    *expr = ExpressionWithoutBlock::Convert {
+                        expression: Box::new(expression),
+                        from,
+                        to,
+                    };
+                    return Type::Number(to);
+                }
+
+                self.expression_without_block(callee);
+                let (parameters, return_type) =
+                    match name.as_deref().and_then(|name| self.lookup_signature(name)) {
+                        Some(signature) => (
+                            signature
+                                .parameters
+                                .iter()
+                                .map(|(_, parameter)| parameter.clone())
+                                .collect(),
+                            signature.return_type.clone(),
+                        ),
+                        None => (vec![], Type::Unknown),
+                    };
+                for (i, argument) in arguments.iter_mut().enumerate() {
+                    let actual = self.expression(argument);
+                    self.coerce(
+                        argument,
+                        actual,
+                        parameters.get(i).unwrap_or(&Type::Unknown),
+                    );
+                }
+                return_type
+            }
+            ExpressionWithoutBlock::Index { callee, index } => {
+                let callee = self.expression_without_block(callee);
+                let actual = self.expression(index);
+                self.coerce(index.as_mut(), actual, &Type::Unknown);
+                match callee {
+                    Type::Array(element) => *element,
+                    _ => Type::Unknown,
+                }
+            }
+            ExpressionWithoutBlock::Field { callee, field } => {
+                match self.expression_without_block(callee) {
+                    Type::Struct(name) => {
+                        let annotation = self.structs[&name]
+                            .iter()
+                            .find(|(name, _)| *name == field.lexeme)
+                            .map(|(_, annotation)| annotation.clone());
+                        self.annotated_type(annotation.as_deref())
+                    }
+                    _ => Type::Unknown,
+                }
+            }
+            ExpressionWithoutBlock::Lambda { parameters, body } => {
+                let scope = parameters
+                    .iter()
+                    .map(|p| {
+                        (
+                            p.name.clone(),
+                            self.annotated_type(p.type_annotation.as_deref()),
+                        )
+                    })
+                    .collect();
+                self.scopes.push(scope);
+                self.expression_with_default(body);
+                self.scopes.pop();
+                Type::Unknown
+            }
+            ExpressionWithoutBlock::Assignment {
+                name,
+                value,
+                number_type,
+                ..
+            } => {
+                let variable = self.lookup(&name.lexeme);
+                let actual = self.expression_without_block(value);
+                if let Type::Number(actual) = self.coerce(value.as_mut(), actual, &variable) {
+                    *number_type = Some(actual);
+                }
+                Type::Unknown
+            }
+            ExpressionWithoutBlock::Html {
+                name,
+                inner,
+                attributes,
+            } => {
+                let parameters = match self.lookup_signature(&name.lexeme) {
+                    Some(signature) => signature.parameters.iter().cloned().collect(),
+                    None => HashMap::new(),
+                };
+                for (name, value) in attributes {
+                    let actual = self.expression(value);
+                    let expected = parameters.get(&name.lexeme).unwrap_or(&Type::Unknown);
+                    self.coerce(value, actual, expected);
+                }
+                for expression in inner {
+                    self.expression_with_default(expression);
+                }
+                Type::Unknown
+            }
+            ExpressionWithoutBlock::Array {
+                elements,
+                element_type,
+            } => {
+                let types = elements
+                    .iter_mut()
+                    .map(|element| self.expression(element))
+                    .collect::<Vec<_>>();
+                let expected = types
+                    .iter()
+                    .find(|actual| matches!(actual, Type::Number(_)))
+                    .cloned()
+                    .unwrap_or(Type::Unknown);
+                let mut element = Type::Unknown;
+                for (expr, actual) in elements.iter_mut().zip(types) {
+                    element = self.coerce(expr, actual, &expected);
+                }
+                if let Type::Number(number_type) = element {
+                    *element_type = Some(number_type);
+                }
+                Type::Array(Box::new(element))
+            }
+            ExpressionWithoutBlock::Tuple { elements } => {
+                for element in elements {
+                    self.expression_with_default(element);
+                }
+                Type::Unknown
+            }
+            ExpressionWithoutBlock::Struct { name, fields } => {
+                let annotations = self.structs.get(&name.lexeme).cloned().unwrap_or_default();
+                for (field, value) in fields {
+                    let annotation = annotations
+                        .iter()
+                        .find(|(name, _)| *name == field.lexeme)
+                        .map(|(_, annotation)| annotation.as_str());
+                    let expected = self.annotated_type(annotation);
+                    let actual = self.expression(value);
+                    self.coerce(value, actual, &expected);
+                }
+                Type::Struct(name.lexeme.clone())
+            }
+            ExpressionWithoutBlock::Convert { to, .. } => Type::Number(*to),
+            ExpressionWithoutBlock::FString { chunks } => {
+                for chunk in chunks {
+                    if let FStringChunk::Expression {
+                        expression,
+                        format,
+                        number_type,
+                    } = chunk
+                    {
+                        if let Type::Number(actual) = self.expression_with_default(expression) {
+                            *number_type = Some(actual);
+                        }
+                        if format.precision.is_some() && *number_type != Some(NumberType::Float) {
+                            self.error(
+                                self.line,
+                                "a precision can only be given for a float".to_string(),
+                            );
+                        }
+                    }
+                }
+                Type::Unknown
+            }
+            ExpressionWithoutBlock::RawJs(_) | ExpressionWithoutBlock::RawGo(_) => Type::Unknown,
+        }
+    }
+}
+
+/// An expression whose untyped integers can be given a type.
+trait Numeric {
+    fn set_number_type(&mut self, number_type: NumberType);
+
+    /// The value of an integer expression made only of literals.
+    fn constant(&self) -> Option<i128>;
+}
+
+/// The expressions numbers are inferred for together by `NumberTransformer::unify`.
+trait NumericExpression: Numeric {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type;
+}
+
+impl Numeric for Expression {
+    fn set_number_type(&mut self, number_type: NumberType) {
+        match self {
+            Expression::WithBlock(expr) => expr.set_number_type(number_type),
+            Expression::WithoutBlock(expr) => expr.set_number_type(number_type),
+        }
+    }
+
+    fn constant(&self) -> Option<i128> {
+        match self {
+            Expression::WithBlock(_) => None,
+            Expression::WithoutBlock(expr) => expr.constant(),
+        }
+    }
+}
+
+impl NumericExpression for Expression {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type {
+        transformer.expression(self)
+    }
+}
+
+impl Numeric for ExpressionWithBlock {
+    fn set_number_type(&mut self, number_type: NumberType) {
+        match self {
+            ExpressionWithBlock::Block(block) => {
+                if let Some(expr) = &mut block.expr {
+                    expr.set_number_type(number_type);
+                }
+            }
+            ExpressionWithBlock::If { then, r#else, .. } => {
+                BranchValue(then).set_number_type(number_type);
+                if let Some(r#else) = r#else {
+                    r#else.set_number_type(number_type);
+                }
+            }
+        }
+    }
+
+    fn constant(&self) -> Option<i128> {
+        None
+    }
+}
+
+impl NumericExpression for ExpressionWithBlock {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type {
+        transformer.expression_with_block(self)
+    }
+}
+
+/// The value of a block that's a branch of an `if`.
+struct BranchValue<'a>(&'a mut BlockExpression);
+
+impl Numeric for BranchValue<'_> {
+    fn set_number_type(&mut self, number_type: NumberType) {
+        if let Some(expr) = &mut self.0.expr {
+            expr.set_number_type(number_type);
+        }
+    }
+
+    fn constant(&self) -> Option<i128> {
+        None
+    }
+}
+
+impl NumericExpression for BranchValue<'_> {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type {
+        transformer.block(self.0)
+    }
+}
+
+impl Numeric for ExpressionWithoutBlock {
+    fn set_number_type(&mut self, number_type: NumberType) {
+        match self {
+            ExpressionWithoutBlock::Literal(Literal::Integer(_, literal_type @ None)) => {
+                *literal_type = Some(number_type);
+            }
+            ExpressionWithoutBlock::Grouping(expr) => expr.set_number_type(number_type),
+            ExpressionWithoutBlock::Unary {
+                operator,
+                right,
+                number_type: unary_type @ None,
+            } if operator.token_type == TokenType::Minus => {
+                *unary_type = Some(number_type);
+                right.set_number_type(number_type);
+            }
+            // Arithmetic on integer literals is done on integers, like it is on integer
+            // variables, so `7 / 2` is `3.0` when a float is expected
+            ExpressionWithoutBlock::Binary {
+                number_type: None, ..
+            } if number_type == NumberType::Float && self.constant().is_some() => {
+                *self = float_literal(self.constant().unwrap() as f64);
+            }
+            ExpressionWithoutBlock::Binary {
+                left,
+                operator,
+                right,
+                number_type: binary_type @ None,
+            } if matches!(
+                operator.token_type,
+                TokenType::Plus | TokenType::Minus | TokenType::Star | TokenType::Slash
+            ) =>
+            {
+                *binary_type = Some(number_type);
+                left.set_number_type(number_type);
+                right.set_number_type(number_type);
+            }
+            _ => {}
+        }
+    }
+
+    fn constant(&self) -> Option<i128> {
+        match self {
+            ExpressionWithoutBlock::Literal(Literal::Integer(_, Some(NumberType::Float))) => None,
+            ExpressionWithoutBlock::Literal(Literal::Integer(value, _)) => Some(*value as i128),
+            ExpressionWithoutBlock::Grouping(expr) => expr.constant(),
+            ExpressionWithoutBlock::Unary {
+                operator, right, ..
+            } if operator.token_type == TokenType::Minus => right.constant().map(|value| -value),
+            ExpressionWithoutBlock::Binary {
+                number_type: Some(NumberType::Float),
+                ..
+            } => None,
+            ExpressionWithoutBlock::Binary {
+                left,
+                operator,
+                right,
+                ..
+            } => {
+                let (left, right) = (left.constant()?, right.constant()?);
+                match operator.token_type {
+                    TokenType::Plus => left.checked_add(right),
+                    TokenType::Minus => left.checked_sub(right),
+                    TokenType::Star => left.checked_mul(right),
+                    TokenType::Slash => left.checked_div(right),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+impl NumericExpression for ExpressionWithoutBlock {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type {
+        transformer.expression_without_block(self)
+    }
+}
+
+/// The literal a conversion of a constant results in, truncating floats and wrapping
+/// integers like the conversion does when it's run.
+fn convert_constant(expression: &Expression, to: NumberType) -> Option<ExpressionWithoutBlock> {
+    let Expression::WithoutBlock(expression) = expression else {
+        return None;
+    };
+    let integer = match (expression.constant(), expression.float_constant()) {
+        (_, Some(value)) if to == NumberType::Float => return Some(float_literal(value)),
+        (Some(value), _) if to == NumberType::Float => return Some(float_literal(value as f64)),
+        (Some(value), _) => value,
+        (None, Some(value)) => value.trunc() as i128,
+        (None, None) => return None,
+    };
+    let integer = match to {
+        NumberType::Int => integer as i32 as i128,
+        _ => integer as i64 as i128,
+    };
+    let literal =
+        ExpressionWithoutBlock::Literal(Literal::Integer(integer.unsigned_abs() as u64, Some(to)));
+    Some(match integer < 0 {
+        true => negate(literal, Some(to)),
+        false => literal,
+    })
+}
+
+fn float_literal(value: f64) -> ExpressionWithoutBlock {
+    let literal = ExpressionWithoutBlock::Literal(Literal::Float(value.abs()));
+    match value < 0.0 {
+        true => negate(literal, Some(NumberType::Float)),
+        false => literal,
+    }
+}
+
+fn negate(expr: ExpressionWithoutBlock, number_type: Option<NumberType>) -> ExpressionWithoutBlock {
+    ExpressionWithoutBlock::Unary {
+        operator: Token::new(TokenType::Minus, "-".to_string(), None, 0),
+        right: Box::new(expr),
+        number_type,
+    }
+}
+
+impl ExpressionWithoutBlock {
+    /// The value of a float literal, which may be negated.
+    fn float_constant(&self) -> Option<f64> {
+        match self {
+            ExpressionWithoutBlock::Literal(Literal::Float(value)) => Some(*value),
+            ExpressionWithoutBlock::Grouping(expr) => expr.float_constant(),
+            ExpressionWithoutBlock::Unary {
+                operator, right, ..
+            } if operator.token_type == TokenType::Minus => right.float_constant().map(|v| -v),
+            _ => None,
+        }
+    }
+
+    fn is_zero(&self) -> bool {
+        match self {
+            ExpressionWithoutBlock::Literal(Literal::Float(value)) => *value == 0.0,
+            ExpressionWithoutBlock::Literal(Literal::Integer(value, _)) => *value == 0,
+            _ => self.constant() == Some(0),
+        }
+    }
+}
+
+impl<T: Numeric> Numeric for Box<T> {
+    fn set_number_type(&mut self, number_type: NumberType) {
+        self.as_mut().set_number_type(number_type)
+    }
+
+    fn constant(&self) -> Option<i128> {
+        self.as_ref().constant()
+    }
+}
+
+impl<T: NumericExpression> NumericExpression for Box<T> {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type {
+        self.as_mut().infer(transformer)
+    }
+}
+
+impl<T: Numeric> Numeric for &mut T {
+    fn set_number_type(&mut self, number_type: NumberType) {
+        (**self).set_number_type(number_type)
+    }
+
+    fn constant(&self) -> Option<i128> {
+        (**self).constant()
+    }
+}
+
+impl<T: NumericExpression> NumericExpression for &mut T {
+    fn infer(&mut self, transformer: &mut NumberTransformer) -> Type {
+        (**self).infer(transformer)
+    }
+}
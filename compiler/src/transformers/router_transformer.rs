@@ -0,0 +1,130 @@
+use super::{walk_ast, AstVisitor};
+use crate::item::{Item, Route};
+use crate::targets::{Module, Program};
+use itertools::Itertools;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// The component that renders the page for a route module.
+const ROUTE_COMPONENT: &str = "Index";
+
+/// Finds the route components of a project and appends a module containing the
+/// router. Routes are file based, `index.fsf` is served at `/`, `about.fsf` at
+/// `/about` and `chirps/[id].fsf` at `/chirps/{id}`. Every route module declares
+/// an `Index` component, which is renamed after its module so they don't clash.
+pub struct RouterTransformer {
+    routes: Vec<Route>,
+    loaders: HashMap<String, String>,
+    current_module: Option<PathBuf>,
+    root: PathBuf,
+}
+
+impl RouterTransformer {
+    pub fn new(root: PathBuf) -> Self {
+        Self {
+            routes: Vec::new(),
+            loaders: HashMap::new(),
+            current_module: None,
+            root,
+        }
+    }
+
+    pub fn transform(&mut self, program: &mut Program) {
+        walk_ast(program, self);
+
+        let mut routes = std::mem::take(&mut self.routes);
+        for route in &mut routes {
+            route.loader_type = self.loaders.get(&route.path).cloned();
+        }
+        // The client matches routes in this order, which is the order the server uses
+        routes.sort_by_cached_key(|route| route_order(&route.path));
+
+        program.push(Module {
+            path: self.root.join("fsf_routes.fsf"),
+            items: vec![Item::Router { routes }],
+            source: None,
+        });
+    }
+
+    fn route_path(&self, module: &Path) -> String {
+        let segments = module
+            .strip_prefix(&self.root)
+            .unwrap()
+            .with_extension("")
+            .iter()
+            .map(|p| p.to_string_lossy().to_string())
+            .collect::<Vec<_>>();
+        let segments = match segments.last().map(String::as_str) {
+            Some("index") => &segments[..segments.len() - 1],
+            _ => &segments[..],
+        };
+        format!("/{}", segments.iter().join("/"))
+    }
+
+    fn route_name(&self, module: &Path, name: &str) -> String {
+        let module_name = module
+            .strip_prefix(&self.root)
+            .unwrap()
+            .with_extension("")
+            .to_string_lossy()
+            .chars()
+            .map(|c| if c.is_alphanumeric() { c } else { '_' })
+            .collect::<String>();
+        format!("{}_{}", module_name, name)
+    }
+}
+
+/// Orders routes so that at the first segment where two routes differ, a static segment
+/// comes before a dynamic one, like `/chirps/new` before `/chirps/[id]`.
+fn route_order(path: &str) -> Vec<(bool, String)> {
+    path.trim_matches('/')
+        .split('/')
+        .map(|segment| {
+            let dynamic = segment.starts_with('[') && segment.ends_with(']');
+            (dynamic, segment.to_string())
+        })
+        .collect()
+}
+
+impl AstVisitor for RouterTransformer {
+    fn visit_module(&mut self, module: &mut Module) {
+        self.current_module = Some(module.path.clone());
+    }
+
+    fn visit_item(&mut self, item: &mut Item) {
+        let module = self.current_module.clone().unwrap();
+        // The standard library is compiled alongside the project but never routed
+        if module.starts_with(self.root.join("std")) {
+            return;
+        }
+
+        match item {
+            Item::Component {
+                name, parameters, ..
+            } if name == ROUTE_COMPONENT => {
+                *name = self.route_name(&module, name);
+                self.routes.push(Route {
+                    path: self.route_path(&module),
+                    module: module
+                        .strip_prefix(&self.root)
+                        .unwrap()
+                        .with_extension("")
+                        .iter()
+                        .map(|p| p.to_string_lossy())
+                        .join("/"),
+                    component: name.clone(),
+                    parameter: parameters.first().map(|p| p.name.clone()),
+                    loader_type: None,
+                });
+            }
+            Item::Loader {
+                name, return_type, ..
+            } if name == ROUTE_COMPONENT => {
+                *name = self.route_name(&module, name);
+                self.loaders
+                    .insert(self.route_path(&module), return_type.clone());
+            }
+            _ => (),
+        }
+    }
+}
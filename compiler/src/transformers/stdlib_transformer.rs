@@ -1,7 +1,59 @@
 use crate::parse_module;
 use crate::targets::Program;
 use std::path::{Path, PathBuf};
-use walkdir::WalkDir;
+use std::sync::OnceLock;
+
+/// The modules of the standard library, which are built into the compiler.
+const STD_LIB: [(&str, &str); 5] = [
+    ("greeting.fsf", include_str!("../../../std/greeting.fsf")),
+    ("react.fsf", include_str!("../../../std/react.fsf")),
+    ("router.fsf", include_str!("../../../std/router.fsf")),
+    ("server.fsf", include_str!("../../../std/server.fsf")),
+    ("testing.fsf", include_str!("../../../std/testing.fsf")),
+];
+
+/// The directory the standard library is written to, so that line directives, source
+/// maps and the language server can point at its files. It is in the user's own cache
+/// directory, where other users can't get at it.
+pub fn std_lib_dir() -> PathBuf {
+    static STD_LIB_DIR: OnceLock<PathBuf> = OnceLock::new();
+    STD_LIB_DIR
+        .get_or_init(|| {
+            let dir = cache_dir()
+                .join(concat!("fsf-", env!("CARGO_PKG_VERSION")))
+                .join("std");
+            // The files are only read to show them, so failing to write them isn't fatal
+            let _ = write_std_lib(&dir);
+            dir.canonicalize().unwrap_or(dir)
+        })
+        .clone()
+}
+
+/// The user's cache directory, as the XDG base directory specification defines it, or
+/// the build output directory of the current one when the user has no home directory.
+fn cache_dir() -> PathBuf {
+    std::env::var_os("XDG_CACHE_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| std::env::home_dir().map(|home| home.join(".cache")))
+        .unwrap_or_else(|| PathBuf::from(".fsf"))
+}
+
+/// Writes the files of the standard library that are missing or outdated. Each is
+/// written to a temporary file that then replaces it, so that compilers running at the
+/// same time never read a partially written file.
+fn write_std_lib(dir: &Path) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for (file_name, contents) in STD_LIB {
+        let path = dir.join(file_name);
+        if std::fs::read_to_string(&path).ok().as_deref() != Some(contents) {
+            let temporary_path = dir.join(format!(".{}.{}", file_name, std::process::id()));
+            std::fs::write(&temporary_path, contents)?;
+            std::fs::rename(temporary_path, path)?;
+        }
+    }
+    Ok(())
+}
 
 pub struct StandardLibraryTransformer {
     root: PathBuf,
@@ -13,27 +65,21 @@ impl StandardLibraryTransformer {
     }
 
     pub fn transform(&mut self, program: &mut Program) -> anyhow::Result<()> {
-        let std_lib_dir = Path::new(concat!(env!("CARGO_MANIFEST_DIR"), "/../std"));
-        let mut modules = vec![];
-
-        for entry in WalkDir::new(std_lib_dir) {
-            let entry = entry?;
-            let path = entry.path();
-
-            // Check if it's a file and has the desired extension
-            if path.is_file() && path.extension().and_then(|s| s.to_str()) == Some("fsf") {
-                let relative_path = path.strip_prefix(std_lib_dir)?;
-                match std::fs::read_to_string(path) {
-                    Ok(contents) => {
-                        let module =
-                            parse_module(contents, self.root.join("std").join(relative_path))?;
-                        modules.push(module);
-                    }
-                    Err(e) => eprintln!("Error reading {}: {}", path.display(), e),
-                }
-            }
-        }
-        program.extend(modules);
+        program.extend(self.modules()?);
         Ok(())
     }
+
+    /// The standard library's modules, as if they were in a `std` directory of the project.
+    pub fn modules(&self) -> anyhow::Result<Program> {
+        let std_lib_dir = std_lib_dir();
+        STD_LIB
+            .iter()
+            .map(|(file_name, contents)| {
+                let mut module =
+                    parse_module(contents.to_string(), self.root.join("std").join(file_name))?;
+                module.source = Some(std_lib_dir.join(file_name));
+                Ok(module)
+            })
+            .collect()
+    }
 }
@@ -1,5 +1,5 @@
 use super::{walk_ast, AstVisitor};
-use crate::item::Item;
+use crate::item::{Attribute, Item};
 use crate::parse_module;
 use crate::targets::{Module, Program};
 use itertools::Itertools;
@@ -10,69 +10,160 @@ const TEST_RUNNER_TEMPLATE: &str = include_str!(concat!(
     "/src/templates/test_runner.fsf"
 ));
 
+struct Test {
+    name: String,
+    ignore: Option<String>,
+    should_panic: Option<String>,
+}
+
+/// The tests of a module, along with its `setup` and `teardown` functions.
+struct TestModule {
+    path: PathBuf,
+    name: String,
+    tests: Vec<Test>,
+    has_setup: bool,
+    has_teardown: bool,
+}
+
+impl TestModule {
+    /// The function added to the module to run its tests. It's named after the module
+    /// since every one is imported into the test runner.
+    fn runner_name(&self) -> String {
+        format!("run_tests_{}", self.name.replace("::", "_"))
+    }
+
+    fn runner(&self) -> String {
+        let tests = self
+            .tests
+            .iter()
+            .map(|test| {
+                // Rust escapes strings the way fsf reads them
+                let options = [
+                    self.has_setup.then(|| "setup = setup".to_string()),
+                    self.has_teardown.then(|| "teardown = teardown".to_string()),
+                    test.ignore
+                        .as_ref()
+                        .map(|reason| format!("ignore = {:?}", reason)),
+                    test.should_panic
+                        .as_ref()
+                        .map(|expected| format!("should_panic = {:?}", expected)),
+                ];
+                let arguments = [
+                    format!("\"{}::{}\"", self.name, test.name),
+                    test.name.clone(),
+                ]
+                .into_iter()
+                .chain(options.into_iter().flatten())
+                .join(", ");
+                format!("__RUN_TEST({});", arguments)
+            })
+            .join("\n");
+        format!("fn {}() -> void {{\n{}\n}}\n", self.runner_name(), tests)
+    }
+}
+
 pub struct TestRunnerTransformer {
-    tests: Vec<(String, String)>,
-    current_module: Option<PathBuf>,
+    modules: Vec<TestModule>,
     root: PathBuf,
+    /// Only tests whose full name contains the filter are run.
+    filter: Option<String>,
 }
 
 impl TestRunnerTransformer {
-    pub fn new(root: PathBuf) -> Self {
+    pub fn new(root: PathBuf, filter: Option<String>) -> Self {
         Self {
-            tests: Vec::new(),
-            current_module: None,
+            modules: Vec::new(),
             root,
+            filter,
         }
     }
 
     pub fn transform(&mut self, program: &mut Program) {
         walk_ast(program, self);
 
-        let imports = self
-            .tests
+        let test_modules = self
+            .modules
+            .iter()
+            .filter(|module| !module.tests.is_empty())
+            .collect::<Vec<_>>();
+        for test_module in &test_modules {
+            let runner = parse_module(test_module.runner(), test_module.path.clone()).unwrap();
+            program
+                .iter_mut()
+                .find(|module| module.path == test_module.path)
+                .unwrap()
+                .items
+                .extend(runner.items);
+        }
+
+        let imports = test_modules
             .iter()
-            .map(|(module, test)| format!("import {}::{};", module, test))
+            .map(|module| format!("import {}::{};", module.name, module.runner_name()))
             .join("\n");
 
-        let tests = self
-            .tests
+        let tests = test_modules
             .iter()
-            .map(|(module, test)| format!("__RUN_TEST(\"{}::{}\", {});", module, test, test))
+            .map(|module| format!("{}();", module.runner_name()))
             .join("\n");
 
         let contents = TEST_RUNNER_TEMPLATE
             .replace("/* replace_imports */", &imports)
             .replace("/* replace_tests */", &tests);
 
-        let test_runner = parse_module(contents, self.root.join("main.fsf")).unwrap();
+        let mut test_runner = parse_module(contents, self.root.join("main.fsf")).unwrap();
+        test_runner.source = None;
         program.push(test_runner);
 
         // TODO: Set up pipelining this transformer with the name transformer before compilation
-        // TODO: Implement __RUN_TEST as a special token that handles catching panics and reporting
-        // test errors
     }
 }
 
 impl AstVisitor for TestRunnerTransformer {
     fn visit_module(&mut self, module: &mut Module) {
-        self.current_module = Some(module.path.clone());
+        let name = module
+            .path
+            .strip_prefix(&self.root)
+            .unwrap()
+            .with_extension("")
+            .iter()
+            .map(|p| p.to_string_lossy())
+            .join("::");
+        self.modules.push(TestModule {
+            path: module.path.clone(),
+            name,
+            tests: Vec::new(),
+            has_setup: false,
+            has_teardown: false,
+        });
     }
 
     fn visit_item(&mut self, item: &mut Item) {
+        let module = self.modules.last_mut().unwrap();
         match item {
-            Item::Function { name, .. } if name.starts_with("test_") => {
-                self.tests.push((
-                    self.current_module
-                        .as_ref()
-                        .unwrap()
-                        .strip_prefix(&self.root)
-                        .unwrap()
-                        .with_extension("")
-                        .iter()
-                        .map(|p| p.to_string_lossy())
-                        .join("::"),
-                    name.clone(),
-                ));
+            Item::Function {
+                name, parameters, ..
+            } if parameters.is_empty() && name == "setup" => module.has_setup = true,
+            Item::Function {
+                name, parameters, ..
+            } if parameters.is_empty() && name == "teardown" => module.has_teardown = true,
+            Item::Function {
+                name, attributes, ..
+            } if name.starts_with("test_") || Attribute::find(attributes, "test").is_some() => {
+                let is_selected = self
+                    .filter
+                    .as_ref()
+                    .is_none_or(|filter| format!("{}::{}", module.name, name).contains(filter));
+                if is_selected {
+                    let value = |attribute| {
+                        Attribute::find(attributes, attribute)
+                            .map(|attribute| attribute.value.clone().unwrap_or_default())
+                    };
+                    module.tests.push(Test {
+                        name: name.clone(),
+                        ignore: value("ignore"),
+                        should_panic: value("should_panic"),
+                    });
+                }
             }
             _ => (),
         }
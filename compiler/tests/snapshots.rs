@@ -0,0 +1,231 @@
+//! Snapshot tests of the generated Go and JavaScript and the diagnostics of every fixture
+//! in `tests/` and `regression_tests/`, and of the documentation generated for `tests/`,
+//! so that changes to code generation can be reviewed without a Go or Node toolchain.
+//!
+//! The snapshots live in `tests/snapshots`. Run with `UPDATE_SNAPSHOTS=1` to write the
+//! current output to them instead of comparing against them.
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+/// A project the compiler is run on, and the command whose pipeline its code is
+/// generated with.
+struct Fixture {
+    path: PathBuf,
+    pipeline: &'static str,
+}
+
+fn repository() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("..")
+}
+
+fn fixtures() -> Vec<Fixture> {
+    let mut fixtures = vec![Fixture {
+        path: PathBuf::from("tests"),
+        pipeline: "test",
+    }];
+
+    let mut regression_tests = std::fs::read_dir(repository().join("regression_tests"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.is_dir() && path.file_name().unwrap() != "bin")
+        .map(|path| path.strip_prefix(repository()).unwrap().to_path_buf())
+        .collect::<Vec<_>>();
+    regression_tests.sort();
+    // Apps with an `index.fsf` route are served rather than run
+    fixtures.extend(regression_tests.into_iter().map(|path| Fixture {
+        pipeline: match repository().join(&path).join("index.fsf").exists() {
+            true => "web",
+            false => "run",
+        },
+        path,
+    }));
+
+    fixtures
+}
+
+/// Runs the compiler from the root of the repository, so that the paths in its output
+/// don't depend on where the repository is checked out. Canonical paths, like the ones
+/// in line directives, are made relative to the repository too, and the ones of the
+/// standard library, which the compiler writes to a cache directory, to that directory.
+fn compiler(args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_compiler"))
+        .args(args)
+        .current_dir(repository())
+        .env("RUST_BACKTRACE", "0")
+        .env("XDG_CACHE_HOME", env!("CARGO_TARGET_TMPDIR"))
+        .output()
+        .unwrap();
+    let output =
+        String::from_utf8(output.stdout).unwrap() + &String::from_utf8(output.stderr).unwrap();
+    let repository = repository().canonicalize().unwrap();
+    let std_lib_parent = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join(concat!("fsf-", env!("CARGO_PKG_VERSION")))
+        .canonicalize()
+        .unwrap();
+    // The cache directory is inside the repository, so it's replaced first
+    output
+        .replace(&format!("{}/", std_lib_parent.display()), "")
+        .replace(&format!("{}/", repository.display()), "")
+}
+
+fn dump(fixture: &Fixture, stage: &str) -> String {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("snapshots")
+        .join(&fixture.path)
+        .join(stage);
+    compiler(&[
+        "dump",
+        fixture.path.to_str().unwrap(),
+        "--stage",
+        stage,
+        "--pipeline",
+        fixture.pipeline,
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ])
+}
+
+fn check(fixture: &Fixture) -> String {
+    compiler(&[
+        "check",
+        fixture.path.to_str().unwrap(),
+        "--message-format",
+        "json",
+    ])
+}
+
+/// The pages of the fixture's documentation. The stylesheet is left out.
+fn doc(fixture: &Fixture) -> String {
+    let out_dir = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join("snapshots")
+        .join(&fixture.path)
+        .join("doc");
+    let output = compiler(&[
+        "doc",
+        fixture.path.to_str().unwrap(),
+        "--out-dir",
+        out_dir.to_str().unwrap(),
+    ]);
+    if !out_dir.join("index.html").exists() {
+        return output;
+    }
+
+    let mut pages = walkdir(&out_dir)
+        .into_iter()
+        .filter(|path| path.extension().is_some_and(|ext| ext == "html"))
+        .collect::<Vec<_>>();
+    pages.sort();
+    pages
+        .iter()
+        .map(|page| {
+            format!(
+                "// {}\n{}",
+                page.strip_prefix(&out_dir).unwrap().display(),
+                std::fs::read_to_string(page).unwrap()
+            )
+        })
+        .collect()
+}
+
+/// The files in a directory and its subdirectories.
+fn walkdir(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .flat_map(|path| match path.is_dir() {
+            true => walkdir(&path),
+            false => vec![path],
+        })
+        .collect()
+}
+
+/// Compares `actual` against the snapshot `name` of each fixture, and reports every
+/// fixture that doesn't match at once.
+fn assert_snapshots(name: &str, fixtures: Vec<Fixture>, actual: impl Fn(&Fixture) -> String) {
+    let update = std::env::var_os("UPDATE_SNAPSHOTS").is_some();
+    let mut failures = Vec::new();
+
+    for fixture in fixtures {
+        let snapshot = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("tests/snapshots")
+            .join(&fixture.path)
+            .join(format!("{}.snap", name));
+        let actual = actual(&fixture);
+
+        if update {
+            std::fs::create_dir_all(snapshot.parent().unwrap()).unwrap();
+            std::fs::write(&snapshot, actual).unwrap();
+            continue;
+        }
+
+        let expected = std::fs::read_to_string(&snapshot).unwrap_or_default();
+        if let Some(difference) = difference(&expected, &actual) {
+            failures.push(format!("{}:\n{}", snapshot.display(), difference));
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} snapshot(s) don't match, run with UPDATE_SNAPSHOTS=1 to update them\n\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+}
+
+/// Describes the first line where `actual` differs from `expected`.
+fn difference(expected: &str, actual: &str) -> Option<String> {
+    if expected == actual {
+        return None;
+    }
+
+    let expected_lines = expected.lines().collect::<Vec<_>>();
+    let actual_lines = actual.lines().collect::<Vec<_>>();
+    let line = expected_lines
+        .iter()
+        .zip(&actual_lines)
+        .position(|(expected, actual)| expected != actual)
+        .unwrap_or(expected_lines.len().min(actual_lines.len()));
+    Some(format!(
+        "  line {}\n  - {}\n  + {}\n",
+        line + 1,
+        expected_lines.get(line).unwrap_or(&"<end of snapshot>"),
+        actual_lines.get(line).unwrap_or(&"<end of output>"),
+    ))
+}
+
+#[test]
+fn go_codegen() {
+    assert_snapshots("go", fixtures(), |fixture| dump(fixture, "go"));
+}
+
+#[test]
+fn js_codegen() {
+    assert_snapshots("js", fixtures(), |fixture| dump(fixture, "js"));
+}
+
+#[test]
+fn diagnostics() {
+    assert_snapshots("diagnostics", fixtures(), check);
+}
+
+/// The fixtures, the standard library and the examples are kept formatted.
+#[test]
+fn formatting() {
+    for dir in ["tests", "std", "examples"] {
+        let output = compiler(&["fmt", "--check", dir]);
+        assert!(output.is_empty(), "{}", output);
+    }
+}
+
+/// Every project's documentation includes the standard library, so only `tests/`, whose
+/// `test_comments.fsf` has the doc comments, is documented.
+#[test]
+fn documentation() {
+    let fixtures = fixtures()
+        .into_iter()
+        .filter(|fixture| fixture.path == Path::new("tests"))
+        .collect();
+    assert_snapshots("doc", fixtures, doc);
+}
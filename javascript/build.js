@@ -1,29 +1,83 @@
-import {build} from 'esbuild';
-import {mkdir} from 'fs/promises';
+import {build, context} from 'esbuild';
+import {mkdir, readdir} from 'fs/promises';
+import {createInterface} from 'readline';
+
+// Release builds are minified, development builds are kept readable for debugging
+const release = process.argv.includes('--release');
+
+// In watch mode the bundle is rebuilt incrementally every time a line is read from
+// stdin, and `rebuilt` or `failed` is written to stdout once it's done
+const watch = process.argv.includes('--watch');
+
+// There is an entry point per route so that each page only loads what it uses
+async function findEntryPoints() {
+    return (await readdir('./entries', {recursive: true}))
+        .filter((entry) => entry.endsWith('.js'))
+        .map((entry) => `./entries/${entry}`)
+        .sort();
+}
+
+function buildOptions(entryPoints) {
+    return {
+        entryPoints,
+        bundle: true,
+        splitting: true,
+        format: 'esm',
+        outdir: './dist',
+        outbase: './entries',
+        minify: release,
+        // Release builds write their source maps next to the bundle without linking them,
+        // and leave out the source of the modules, which includes the server only code of
+        // loaders and actions
+        sourcemap: release ? 'external' : true,
+        sourcesContent: !release,
+        target: 'es2020'
+    };
+}
+
+async function watchBuild() {
+    let entryPoints = await findEntryPoints();
+    let ctx = await context(buildOptions(entryPoints));
+
+    for await (const _ of createInterface({input: process.stdin})) {
+        try {
+            // Adding or removing a route changes the entry points, which needs a new context
+            const currentEntryPoints = await findEntryPoints();
+            if (currentEntryPoints.join() !== entryPoints.join()) {
+                await ctx.dispose();
+                entryPoints = currentEntryPoints;
+                ctx = await context(buildOptions(entryPoints));
+            }
+            await ctx.rebuild();
+            console.log('rebuilt');
+        } catch (error) {
+            console.error('❌ Build failed:', error.message);
+            console.log('failed');
+        }
+    }
+
+    await ctx.dispose();
+}
 
 // Main build function
 async function main() {
-    console.log('🏗️  FSF Framework Build\n');
+    await mkdir('./dist', {recursive: true});
 
-    console.log('📦 Building single bundle from index.jsx...');
+    if (watch) {
+        await watchBuild();
+        return;
+    }
 
-    await mkdir('./dist', {recursive: true});
+    console.log(`🏗️  FSF Framework Build${release ? ' (release)' : ''}\n`);
+
+    const entryPoints = await findEntryPoints();
+    console.log(`📦 Building ${entryPoints.length} route entry points...`);
 
     try {
-        await build({
-            entryPoints: ['./index.jsx'],
-            bundle: true,
-            format: 'esm',
-            outfile: './dist/bundle.js',
-            jsx: 'automatic',
-            jsxImportSource: 'react',
-            minify: false, // Keep readable for debugging
-            sourcemap: false,
-            target: 'es2020'
-        });
-
-        console.log('✅ Built ./dist/bundle.js');
-        console.log('\n🎯 Usage: Include <script type="module" src="/bundle.js"></script> in your HTML');
+        await build(buildOptions(entryPoints));
+
+        console.log('✅ Built ./dist');
+        console.log('\n🎯 Usage: Include <script type="module" src="/static/{route}.js"></script> in your HTML');
 
     } catch (error) {
         console.error('❌ Build failed:', error.message);
@@ -31,4 +85,4 @@ async function main() {
     }
 }
 
-main().catch(console.error);
\ No newline at end of file
+main().catch(console.error);
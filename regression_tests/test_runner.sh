@@ -155,10 +155,16 @@ run_test() {
         return 1
     fi
 
+    # Apps with an `index.fsf` route are served rather than run, so they are checked
+    local command="run"
+    if [ -f "$test_dir/index.fsf" ]; then
+        command="check"
+    fi
+
     # Run the compiler and capture both stdout and stderr
-    if ! "$COMPILER_PATH" run "$test_dir" > "$temp_output" 2>&1; then
+    if ! "$COMPILER_PATH" "$command" "$test_dir" > "$temp_output" 2>&1; then
         # If compiler fails, still capture the output for comparison
-        "$COMPILER_PATH" run "$test_dir" > "$temp_output" 2>&1 || true
+        "$COMPILER_PATH" "$command" "$test_dir" > "$temp_output" 2>&1 || true
     fi
 
     # If overwrite mode, update the expected output file
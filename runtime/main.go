@@ -1,12 +1,24 @@
 package main
 
 import (
+	"embed"
+	"encoding/json"
+	"fmt"
+	"io/fs"
 	"log"
 	"net/http"
+	"os"
 	"strings"
 	"html/template"
 )
 
+// The bundled JavaScript and static assets are embedded so the server is a single binary
+//go:embed all:javascript/dist
+var staticFiles embed.FS
+
+// liveReload is set by `fsf serve --watch`, which restarts the server on every change
+var liveReload = os.Getenv("FSF_LIVE_RELOAD") != ""
+
 const htmlTemplate = `<!DOCTYPE html>
 <html lang="en">
 <head>
@@ -21,114 +33,113 @@ const htmlTemplate = `<!DOCTYPE html>
 </head>
 <body>
     <div id="root">{{.ServerRenderedContent}}</div>
-    <!-- Single bundle with everything included -->
-    <script type="module" src="/static/bundle.js"></script>
+    {{if .Entry}}
+    <!-- Entry point of the route, which loads the code split chunks it needs -->
+    <script type="module" src="/static/{{.Entry}}.js"></script>
+    {{end}}
+    {{if .LiveReload}}
+    <script>
+        // The stream reconnects once the dev server has restarted with the latest changes
+        const reload = new EventSource("/__reload");
+        let connected = false;
+        reload.onopen = () => {
+            if (connected) {
+                location.reload();
+            }
+            connected = true;
+        };
+    </script>
+    {{end}}
 </body>
 </html>`
 
-// HTMLBuilder provides a simple API for building HTML strings
-type HTMLBuilder struct {
-	elements []string // Stack to track open elements
-	buffer   strings.Builder
-}
-
-// NewHTMLBuilder creates a new HTMLBuilder instance
-func NewHTMLBuilder() *HTMLBuilder {
-	return &HTMLBuilder{
-		elements: make([]string, 0),
-	}
-}
-
-// beginElement starts a new HTML element
-func (h *HTMLBuilder) beginElement(name string) {
-	h.buffer.WriteString("<")
-	h.buffer.WriteString(name)
-	h.buffer.WriteString(">")
-	h.elements = append(h.elements, name)
-}
-
-// endElement closes the most recently opened element
-func (h *HTMLBuilder) endElement() {
-	if len(h.elements) == 0 {
-		return // No elements to close
-	}
-
-	// Pop the last element from the stack
-	lastIndex := len(h.elements) - 1
-	elementName := h.elements[lastIndex]
-	h.elements = h.elements[:lastIndex]
-
-	// Write the closing tag
-	h.buffer.WriteString("</")
-	h.buffer.WriteString(elementName)
-	h.buffer.WriteString(">")
-}
-
-// addString adds text content to the current element
-func (h *HTMLBuilder) addString(text string) {
-	h.buffer.WriteString(text)
-}
-
-// build returns the final HTML string
-func (h *HTMLBuilder) build() string {
-	return h.buffer.String()
-}
-
-// Reset clears the builder for reuse
-func (h *HTMLBuilder) Reset() {
-	h.elements = h.elements[:0]
-	h.buffer.Reset()
-}
-
-// TODO: Delete once we have tree pruning. This is to avoid unused variables
-// in Go.
-func (h *HTMLBuilder) addAttribute(name string, value interface{}) {
-}
-
 func h1(w http.ResponseWriter, r *http.Request) {
     tmpl := template.Must(template.New("page").Parse(htmlTemplate))
-//     io.WriteString(w, Index())
-    serverContent := renderCurrentRoute(r.URL.Path)
 
-    // Optional: Add any initial data
-    initialData := getInitialDataForRoute(r.URL.Path)
+    route, params, ok := matchRoute(routes, r.URL.Path)
+    if !ok {
+        w.WriteHeader(http.StatusNotFound)
+        tmpl.Execute(w, struct {
+            Title                 string
+            ServerRenderedContent template.HTML
+            InitialData          template.JS
+            Entry                 string
+            LiveReload            bool
+        }{
+            Title:                 "FSF App",
+            ServerRenderedContent: template.HTML(`<div>404 - Page not found</div>`),
+            LiveReload:            liveReload,
+        })
+        return
+    }
+
+    // The loader result is rendered on the server and handed to the client to hydrate with
+    serverContent, loaderData := renderRoute(route, params)
+    initialData, err := json.Marshal(map[string]any{
+        "route":  r.URL.Path,
+        "params": params,
+        "data":   loaderData,
+    })
+    if err != nil {
+        http.Error(w, err.Error(), http.StatusInternalServerError)
+        return
+    }
 
     data := struct {
         Title                 string
         ServerRenderedContent template.HTML
         InitialData          template.JS
+        Entry                 string
+        LiveReload            bool
     }{
         Title:                 "FSF App",
         ServerRenderedContent: template.HTML(serverContent),
         InitialData:          template.JS(initialData),
+        Entry:                 route.entry,
+        LiveReload:            liveReload,
     }
 
     w.Header().Set("Content-Type", "text/html")
     tmpl.Execute(w, data)
 }
 
-func renderCurrentRoute(path string) string {
-	switch path {
-	case "/":
-        return Index();
-	default:
-		return `<div>404 - Page not found</div>`
+// loaderHandler serves the loader result of a route as JSON for client side navigations
+func loaderHandler(w http.ResponseWriter, r *http.Request) {
+	route, params, ok := matchRoute(routes, strings.TrimPrefix(r.URL.Path, "/__loaders"))
+	if !ok {
+		http.NotFound(w, r)
+		return
 	}
+
+	w.Header().Set("Content-Type", "application/json")
+	json.NewEncoder(w).Encode(loadRoute(route, params))
 }
 
-// Optional: Get initial data for hydration
-func getInitialDataForRoute(path string) string {
-	// TODO: Return any initial data as JSON string
-	return `{"timestamp": "` + "2025-06-11T12:00:00Z" + `"}`
+// reloadHandler holds an event stream open until the dev server restarts the server
+func reloadHandler(w http.ResponseWriter, r *http.Request) {
+	w.Header().Set("Content-Type", "text/event-stream")
+	w.Header().Set("Cache-Control", "no-cache")
+	fmt.Fprint(w, "retry: 250\n\n")
+	w.(http.Flusher).Flush()
+	<-r.Context().Done()
 }
 
 func main() {
-    http.Handle("/static/", http.StripPrefix("/static/", http.FileServer(http.Dir("./javascript/dist/"))))
+	static, err := fs.Sub(staticFiles, "javascript/dist")
+	if err != nil {
+		log.Fatal(err)
+	}
+	http.Handle("/static/", http.StripPrefix("/static/", http.FileServer(http.FS(static))))
+	http.HandleFunc("/__actions/", handleServerAction)
+	http.HandleFunc("/__loaders/", loaderHandler)
+	if liveReload {
+		http.HandleFunc("/__reload", reloadHandler)
+	}
 	http.HandleFunc("/", h1)
 
 	// Start the server on port 8080
 	log.Println("Starting server on :8080")
-	err := http.ListenAndServe(":8080", nil)
+	err = http.ListenAndServe(":8080", nil)
 	if err != nil {
 		log.Fatal("ListenAndServe: ", err)
 	}